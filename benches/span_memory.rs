@@ -0,0 +1,164 @@
+//! Measures the memory used by tracing a plate into the packed `SpanGrid`,
+//! and compares the grid against per-pixel `Vec` span storage.
+//!
+//! Defaults to a 4K plate; pass a resolution to try others, e.g.
+//! `cargo bench --bench span_memory -- 11520 5120` for a 12K LCD.
+
+use rs_licer::spans::Span;
+use rs_licer::trace_spans;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAlloc;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    // Growing a large buffer remaps it rather than copying, so only the
+    // change in size counts.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            if new_size >= layout.size() {
+                let now = CURRENT.fetch_add(new_size - layout.size(), Ordering::Relaxed) + new_size - layout.size();
+                PEAK.fetch_max(now, Ordering::Relaxed);
+            } else {
+                CURRENT.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+            }
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Pixel size the fixture is traced at, in micrometers.
+const PIXEL_UM: f32 = 50.0;
+
+/// The triangles of an axis-aligned box, facing out, or in if `inward`.
+fn box_faces(min: [f32; 3], max: [f32; 3], inward: bool) -> Vec<stl_io::Triangle> {
+    let corner = |i: usize| {
+        stl_io::Vertex::new([
+            if i & 1 == 0 { min[0] } else { max[0] },
+            if i & 2 == 0 { min[1] } else { max[1] },
+            if i & 4 == 0 { min[2] } else { max[2] },
+        ])
+    };
+    // Corner quads of each side, counter-clockwise seen from outside.
+    let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    quads
+        .iter()
+        .flat_map(|q| [[q[0], q[1], q[2]], [q[0], q[2], q[3]]])
+        .map(|[a, b, c]| {
+            let (b, c) = if inward { (c, b) } else { (b, c) };
+            stl_io::Triangle { normal: stl_io::Normal::new([0.0; 3]), vertices: [corner(a), corner(b), corner(c)] }
+        })
+        .collect()
+}
+
+/// Writes a part filling a `width` x `height` pixel plate: a box with a
+/// sealed cavity in its middle third, which gives central pixels two spans.
+fn write_fixture(path: &std::path::Path, width: u32, height: u32) {
+    let (w, h) = (width as f32 * PIXEL_UM / 1000.0, height as f32 * PIXEL_UM / 1000.0);
+    let mut faces = box_faces([0.0, 0.0, 0.0], [w, h, 40.0], false);
+    faces.extend(box_faces([w / 3.0, h / 3.0, 10.0], [2.0 * w / 3.0, 2.0 * h / 3.0, 30.0], true));
+    let mut file = std::fs::File::create(path).expect("fixture is writable");
+    stl_io::write_stl(&mut file, faces.iter()).expect("fixture is writable");
+}
+
+struct Measurement {
+    retained: usize,
+    peak: usize,
+    allocations: usize,
+    seconds: f64,
+}
+
+fn measure<T>(build: impl FnOnce() -> T) -> (T, Measurement) {
+    let base = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(base, Ordering::Relaxed);
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let value = build();
+    let seconds = start.elapsed().as_secs_f64();
+    let measurement = Measurement {
+        retained: CURRENT.load(Ordering::Relaxed) - base,
+        peak: PEAK.load(Ordering::Relaxed) - base,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        seconds,
+    };
+    (value, measurement)
+}
+
+fn report(name: &str, m: &Measurement) {
+    let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<14} retained {:>9.1} MiB  peak {:>9.1} MiB  allocations {:>10}  {:>6.2}s",
+        name,
+        mib(m.retained),
+        mib(m.peak),
+        m.allocations,
+        m.seconds
+    );
+}
+
+fn main() {
+    let dims: Vec<u32> = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    let (width, height) = match dims.as_slice() {
+        [w, h, ..] => (*w, *h),
+        _ => (3840, 2400),
+    };
+    println!("Span storage for a {} x {} plate", width, height);
+
+    let fixture = std::env::temp_dir().join(format!("span_memory_{}x{}.stl", width, height));
+    write_fixture(&fixture, width, height);
+    let path = fixture.to_str().expect("temporary path is UTF-8");
+
+    let (grid, traced_m) = measure(|| trace_spans(path, PIXEL_UM).expect("fixture slices"));
+    report("trace_spans", &traced_m);
+    println!("{} spans stored", grid.span_count());
+    println!(
+        "Peak while tracing {:.2}x the packed grid",
+        traced_m.peak as f64 / traced_m.retained.max(1) as f64
+    );
+
+    let (legacy, legacy_m) = measure(|| {
+        let mut pixels: Vec<Vec<Span>> = Vec::with_capacity((grid.width() * grid.height()) as usize);
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                pixels.push(grid.pixel(x, y).to_vec());
+            }
+        }
+        pixels
+    });
+    report("Vec<Vec<Span>>", &legacy_m);
+    drop(legacy);
+
+    println!(
+        "Retained memory reduced {:.1}x",
+        legacy_m.retained as f64 / traced_m.retained.max(1) as f64
+    );
+    let _ = std::fs::remove_file(&fixture);
+}
@@ -0,0 +1,376 @@
+//! Checks that need the rendered layers in print order.
+//!
+//! Layers are rendered in parallel and finish out of order, so each one is
+//! held until every layer below it has been checked. Engines render in
+//! chunks of ordered layers, which keeps the number held small. Work that
+//! needs no other layer is done as a layer arrives, so only the steps that
+//! join it to the layer below wait for the others.
+
+use crate::cavity::{CavitySweep, EmptyRegions};
+use crate::layers::Layer;
+use crate::{layer_file_name, PlateGrid, SliceError, SlicerConfig};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Directory under the output directory that island overlays go in.
+const OVERLAY_DIR: &str = "islands";
+
+/// Search for islands: lit regions that do not overlap the layer below and
+/// so would cure onto nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct IslandCheck {
+    /// Save an image of each layer with islands, marking them in red over
+    /// the layer below, in an `islands` directory of the output.
+    pub overlays: bool,
+    /// Fail the job if any island is found.
+    pub strict: bool,
+}
+
+/// A lit region with nothing under it in the layer below.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Island {
+    pub layer: u32,
+    pub z_mm: f32,
+    /// Center of the region, in model millimeters.
+    pub centroid_mm: [f32; 2],
+    pub area_mm2: f32,
+}
+
+impl fmt::Display for Island {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Island of {:.3} mm² at ({:.2}, {:.2}) mm in layer {} (Z {:.3} mm)",
+            self.area_mm2, self.centroid_mm[0], self.centroid_mm[1], self.layer, self.z_mm
+        )
+    }
+}
+
+/// An empty region that the printed layers close off from the vat, which
+/// pulls on the model as each layer peels away.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EnclosedCavity {
+    pub first_layer: u32,
+    pub last_layer: u32,
+    pub z_min_mm: f32,
+    pub z_max_mm: f32,
+    pub volume_mm3: f32,
+    /// Middle of the cavity's lowest cross-section, in model millimeters.
+    pub position_mm: [f32; 2],
+    /// Whether the build plate seals the cavity from below, making it a
+    /// suction cup rather than a closed void.
+    pub on_plate: bool,
+}
+
+impl fmt::Display for EnclosedCavity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {:.1} mm³ at ({:.2}, {:.2}) mm, Z {:.3} to {:.3} mm (layers {} to {})",
+            if self.on_plate { "Suction cup on the build plate" } else { "Enclosed void" },
+            self.volume_mm3,
+            self.position_mm[0],
+            self.position_mm[1],
+            self.z_min_mm,
+            self.z_max_mm,
+            self.first_layer,
+            self.last_layer
+        )
+    }
+}
+
+/// What the checks found over all layers.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Findings {
+    pub(crate) cavities: Vec<EnclosedCavity>,
+    pub(crate) islands: Vec<Island>,
+}
+
+pub(crate) struct LayerAnalysis<'a> {
+    grid: &'a PlateGrid,
+    cavity_check: bool,
+    island_check: bool,
+    state: Mutex<State>,
+}
+
+struct State {
+    /// Index of the next layer to check.
+    next: u32,
+    /// Layers waiting for the ones below them.
+    pending: BTreeMap<u32, Pending>,
+    cavities: Option<CavitySweep>,
+    islands: Option<IslandSearch>,
+}
+
+/// A layer as it arrives, with what can be worked out from it alone.
+struct Pending {
+    layer: Layer,
+    empty: Option<EmptyRegions>,
+    /// The solid mask and its lit regions.
+    lit: Option<(Vec<bool>, Vec<Vec<usize>>)>,
+}
+
+struct IslandSearch {
+    /// Solid mask of the last layer checked, from the first one that is
+    /// not empty.
+    below: Option<Vec<bool>>,
+    overlay_dir: Option<PathBuf>,
+    found: Vec<Island>,
+}
+
+/// A layer with islands whose overlay is drawn and saved once the lock is
+/// released.
+struct Overlay {
+    path: PathBuf,
+    solid: Vec<bool>,
+    below: Vec<bool>,
+    islands: Vec<Vec<usize>>,
+}
+
+impl<'a> LayerAnalysis<'a> {
+    /// Sets up the checks enabled in `config`, or returns `None` if there
+    /// are none.
+    pub(crate) fn new(config: &SlicerConfig, grid: &'a PlateGrid) -> Result<Option<Self>, SliceError> {
+        let cavities = config.cavity_check.then(CavitySweep::new);
+        let islands = match config.islands {
+            Some(check) => {
+                let overlay_dir = check.overlays.then(|| Path::new(&config.output_dir).join(OVERLAY_DIR));
+                if let Some(dir) = &overlay_dir {
+                    fs::create_dir_all(dir).map_err(SliceError::io("Could not create island overlay directory"))?;
+                }
+                Some(IslandSearch { below: None, overlay_dir, found: Vec::new() })
+            }
+            None => None,
+        };
+        if cavities.is_none() && islands.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            grid,
+            cavity_check: config.cavity_check,
+            island_check: config.islands.is_some(),
+            state: Mutex::new(State { next: 0, pending: BTreeMap::new(), cavities, islands }),
+        }))
+    }
+
+    /// Adds a rendered layer, as written before orientation, and checks it
+    /// along with any layers above it that were waiting for it.
+    pub(crate) fn add(&self, layer: &Layer, img: &image::GrayImage) -> Result<(), SliceError> {
+        let (width, height) = (self.grid.width_px, self.grid.height_px);
+        let solid: Vec<bool> = img.pixels().map(|pixel| pixel.0[0] != 0).collect();
+        let empty = self.cavity_check.then(|| EmptyRegions::label(&solid, width, height));
+        let lit = self.island_check.then(|| {
+            let regions = lit_regions(&solid, width as usize, height as usize);
+            (solid, regions)
+        });
+        let mut overlays = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            let state = &mut *state;
+            state.pending.insert(layer.index, Pending { layer: layer.clone(), empty, lit });
+            while let Some(pending) = state.pending.remove(&state.next) {
+                if let (Some(sweep), Some(empty)) = (&mut state.cavities, pending.empty) {
+                    sweep.push(empty, self.grid.voxel_mm3(&pending.layer));
+                }
+                if let (Some(search), Some((solid, regions))) = (&mut state.islands, pending.lit) {
+                    overlays.extend(search.check(self.grid, &pending.layer, solid, regions));
+                }
+                state.next += 1;
+            }
+        }
+        // Drawn and saved once the lock is released, so other layers need
+        // not wait.
+        for overlay in overlays {
+            overlay.draw(self.grid).save(&overlay.path).map_err(SliceError::Image)?;
+        }
+        Ok(())
+    }
+
+    /// Ends the checks once every layer has been added.
+    pub(crate) fn finish(self, layers: &[Layer]) -> Findings {
+        let state = self.state.into_inner().unwrap();
+        let grid = self.grid;
+        let cavities = state.cavities.map(CavitySweep::finish).unwrap_or_default();
+        Findings {
+            islands: state.islands.map(|search| search.found).unwrap_or_default(),
+            cavities: cavities
+                .into_iter()
+                .map(|cavity| {
+                    let (first, last) = (&layers[cavity.lowest_layer], &layers[cavity.highest_layer]);
+                    // Image row 0 is at the plate's maximum Y.
+                    let (x, row) = cavity.lowest_pixel;
+                    EnclosedCavity {
+                        first_layer: first.index,
+                        last_layer: last.index,
+                        z_min_mm: first.z,
+                        z_max_mm: last.z + last.thickness_mm,
+                        volume_mm3: cavity.volume_mm3 as f32,
+                        position_mm: [
+                            grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm,
+                            grid.min_bound.y + ((grid.height_px - 1 - row) as f32 + 0.5) * grid.pixel_size_y_mm,
+                        ],
+                        on_plate: cavity.lowest_layer == 0,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The checks hold masks and labels for every layer still waiting for the
+/// ones below it, and the cavity sweep keeps a record of every empty region
+/// it has seen, none of which a memory budget accounts for.
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    if (config.cavity_check || config.islands.is_some()) && config.memory_budget_mb.is_some() {
+        return Err(SliceError::InvalidConfig(
+            "the cavity and island checks cannot run under a memory budget".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+impl IslandSearch {
+    /// Records the islands of the next layer up, given its solid mask and
+    /// lit regions, and returns its overlay if it has islands and overlays
+    /// are wanted.
+    fn check(&mut self, grid: &PlateGrid, layer: &Layer, solid: Vec<bool>, regions: Vec<Vec<usize>>) -> Option<Overlay> {
+        // The first layer with anything in it rests on the plate.
+        if self.below.is_none() {
+            if !regions.is_empty() {
+                self.below = Some(solid);
+            }
+            return None;
+        }
+        let below = self.below.replace(solid).unwrap_or_default();
+        let islands = unsupported(regions, &below);
+        if islands.is_empty() {
+            return None;
+        }
+
+        let (width, height) = (grid.width_px as usize, grid.height_px as usize);
+        let pixel_area_mm2 = grid.pixel_size_x_mm * grid.pixel_size_y_mm;
+        for region in &islands {
+            let count = region.len() as f64;
+            let (sum_x, sum_row) = region
+                .iter()
+                .fold((0.0, 0.0), |(sx, sr), &i| (sx + (i % width) as f64, sr + (i / width) as f64));
+            let (x, row) = ((sum_x / count) as f32, (sum_row / count) as f32);
+            self.found.push(Island {
+                layer: layer.index,
+                z_mm: layer.z,
+                centroid_mm: [
+                    grid.min_bound.x + (x + 0.5) * grid.pixel_size_x_mm,
+                    // Image row 0 is at the plate's maximum Y.
+                    grid.min_bound.y + (height as f32 - 0.5 - row) * grid.pixel_size_y_mm,
+                ],
+                area_mm2: region.len() as f32 * pixel_area_mm2,
+            });
+        }
+
+        let dir = self.overlay_dir.as_ref()?;
+        Some(Overlay {
+            path: dir.join(layer_file_name(layer, "png")),
+            solid: self.below.clone().unwrap_or_default(),
+            below,
+            islands,
+        })
+    }
+}
+
+impl Overlay {
+    /// The layer in white over the one below in gray, islands in red.
+    fn draw(&self, grid: &PlateGrid) -> image::RgbImage {
+        let width = grid.width_px as usize;
+        let mut overlay = image::RgbImage::from_fn(grid.width_px, grid.height_px, |x, y| {
+            let i = y as usize * width + x as usize;
+            match (self.solid[i], self.below[i]) {
+                (true, _) => image::Rgb([255, 255, 255]),
+                (false, true) => image::Rgb([96, 96, 96]),
+                (false, false) => image::Rgb([0, 0, 0]),
+            }
+        });
+        for &i in self.islands.iter().flatten() {
+            overlay.put_pixel((i % width) as u32, (i / width) as u32, image::Rgb([255, 0, 0]));
+        }
+        overlay
+    }
+}
+
+/// The 8-connected lit regions of `solid` that share no pixel with `below`,
+/// as lists of row-major pixel indices.
+pub(crate) fn unsupported_regions(solid: &[bool], below: &[bool], width: usize, height: usize) -> Vec<Vec<usize>> {
+    unsupported(lit_regions(solid, width, height), below)
+}
+
+/// The regions that share no pixel with `below`.
+fn unsupported(regions: Vec<Vec<usize>>, below: &[bool]) -> Vec<Vec<usize>> {
+    regions.into_iter().filter(|region| !region.iter().any(|&i| below[i])).collect()
+}
+
+/// The 8-connected lit regions of `solid`, as lists of row-major pixel
+/// indices.
+fn lit_regions(solid: &[bool], width: usize, height: usize) -> Vec<Vec<usize>> {
+    let mut seen = vec![false; solid.len()];
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..solid.len() {
+        if !solid[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        stack.push(start);
+        let mut members = Vec::new();
+        while let Some(i) = stack.pop() {
+            members.push(i);
+            let (x, y) = (i % width, i / width);
+            for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                for nx in x.saturating_sub(1)..(x + 2).min(width) {
+                    let j = ny * width + nx;
+                    if solid[j] && !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        regions.push(members);
+    }
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A layer mask from rows of `#` for lit pixels and `.` for dark ones.
+    fn mask(rows: &[&str]) -> Vec<bool> {
+        rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect()
+    }
+
+    #[test]
+    fn finds_regions_with_nothing_below() {
+        let below = mask(&[
+            "##......",
+            "##......",
+            "........",
+            "........",
+        ]);
+        // An overhang reaching out from the supported corner, which stays
+        // connected through a diagonal step, and a separate island.
+        let solid = mask(&[
+            "####....",
+            "...#..##",
+            "....#.##",
+            "........",
+        ]);
+        let regions = unsupported_regions(&solid, &below, 8, 4);
+        assert_eq!(regions.len(), 1);
+        let mut island = regions[0].clone();
+        island.sort();
+        assert_eq!(island, [14, 15, 22, 23]);
+    }
+}
@@ -0,0 +1,210 @@
+//! Finding enclosed empty regions by sweeping up through the layers.
+//!
+//! Each layer's empty pixels are split into 4-connected regions, and regions
+//! overlapping one in the layer below are merged, so only two layers are
+//! held at a time. A region is open if it reaches the edge of a layer or
+//! the top of the stack. Below the first layer is the build plate, which
+//! seals whatever rests on it.
+
+/// A connected empty region that never reaches the outside.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Cavity {
+    /// Index of the lowest layer the cavity reaches.
+    pub(crate) lowest_layer: usize,
+    pub(crate) highest_layer: usize,
+    /// A pixel of the cavity in its lowest layer, near the middle of its
+    /// cross-section, as `(x, y)` in the layers' pixel coordinates.
+    pub(crate) lowest_pixel: (u32, u32),
+    pub(crate) volume_mm3: f64,
+}
+
+/// What is known about a region, valid at union-find roots.
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    open: bool,
+    lowest_layer: usize,
+    highest_layer: usize,
+    lowest_pixel: (u32, u32),
+    volume_mm3: f64,
+}
+
+/// The empty regions of one layer, labeled on their own so that layers can
+/// be labeled in any order and merged into the sweep in print order.
+pub(crate) struct EmptyRegions {
+    /// Region of each pixel, numbered from 0 within the layer, or `SOLID`.
+    labels: Vec<u32>,
+    regions: Vec<LayerRegion>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LayerRegion {
+    open: bool,
+    /// The pixel nearest the region's centroid.
+    middle: (u32, u32),
+    pixels: usize,
+}
+
+impl EmptyRegions {
+    /// Splits a layer's empty pixels into 4-connected regions, given whether
+    /// each pixel is solid in row-major order.
+    pub(crate) fn label(solid: &[bool], width: u32, height: u32) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let mut labels = vec![SOLID; width * height];
+        let mut regions = Vec::new();
+        let mut stack = Vec::new();
+        let mut members = Vec::new();
+
+        for start in 0..width * height {
+            if solid[start] || labels[start] != SOLID {
+                continue;
+            }
+            let label = regions.len() as u32;
+            labels[start] = label;
+            stack.push(start);
+            members.clear();
+            let mut open = false;
+            while let Some(i) = stack.pop() {
+                members.push(i);
+                let (x, y) = (i % width, i / width);
+                open |= x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+                let neighbors = [
+                    (x > 0).then(|| i - 1),
+                    (x + 1 < width).then(|| i + 1),
+                    (y > 0).then(|| i - width),
+                    (y + 1 < height).then(|| i + width),
+                ];
+                for j in neighbors.into_iter().flatten() {
+                    if !solid[j] && labels[j] == SOLID {
+                        labels[j] = label;
+                        stack.push(j);
+                    }
+                }
+            }
+
+            let count = members.len() as f64;
+            let (sum_x, sum_y) = members
+                .iter()
+                .fold((0.0, 0.0), |(sx, sy), &i| (sx + (i % width) as f64, sy + (i / width) as f64));
+            let (cx, cy) = (sum_x / count, sum_y / count);
+            let middle = members
+                .iter()
+                .copied()
+                .min_by(|&a, &b| {
+                    let d = |i: usize| ((i % width) as f64 - cx).powi(2) + ((i / width) as f64 - cy).powi(2);
+                    d(a).total_cmp(&d(b))
+                })
+                .unwrap_or(start);
+            regions.push(LayerRegion {
+                open,
+                middle: ((middle % width) as u32, (middle / width) as u32),
+                pixels: members.len(),
+            });
+        }
+        Self { labels, regions }
+    }
+}
+
+pub(crate) struct CavitySweep {
+    layers: usize,
+    /// The last layer pushed, and the label its first region was given.
+    top: Option<(EmptyRegions, u32)>,
+    parents: Vec<u32>,
+    regions: Vec<Region>,
+}
+
+const SOLID: u32 = u32::MAX;
+
+impl CavitySweep {
+    pub(crate) fn new() -> Self {
+        Self { layers: 0, top: None, parents: Vec::new(), regions: Vec::new() }
+    }
+
+    /// Adds the next layer up, given its empty regions and the volume of one
+    /// of its pixels, joining regions that overlap one in the layer below.
+    pub(crate) fn push(&mut self, layer: EmptyRegions, voxel_mm3: f64) {
+        let base = self.parents.len() as u32;
+        for (offset, region) in layer.regions.iter().enumerate() {
+            self.parents.push(base + offset as u32);
+            self.regions.push(Region {
+                open: region.open,
+                lowest_layer: self.layers,
+                highest_layer: self.layers,
+                lowest_pixel: region.middle,
+                volume_mm3: region.pixels as f64 * voxel_mm3,
+            });
+        }
+
+        if let Some((below, below_base)) = self.top.take() {
+            let mut last_joined = (SOLID, SOLID);
+            for (&label, &under) in layer.labels.iter().zip(&below.labels) {
+                if label != SOLID && under != SOLID && (label, under) != last_joined {
+                    self.union(base + label, below_base + under);
+                    last_joined = (label, under);
+                }
+            }
+        }
+        self.top = Some((layer, base));
+        self.layers += 1;
+    }
+
+    /// Ends the sweep, opening everything in the top layer to the air above,
+    /// and returns the cavities from the bottom up.
+    pub(crate) fn finish(mut self) -> Vec<Cavity> {
+        if let Some((top, base)) = self.top.take() {
+            for offset in 0..top.regions.len() as u32 {
+                let root = self.find(base + offset);
+                self.regions[root as usize].open = true;
+            }
+        }
+        let roots: Vec<u32> = (0..self.parents.len() as u32)
+            .filter(|&label| self.parents[label as usize] == label)
+            .collect();
+        let mut cavities: Vec<Cavity> = roots
+            .into_iter()
+            .filter(|&label| !self.regions[label as usize].open)
+            .map(|label| {
+                let region = self.regions[label as usize];
+                Cavity {
+                    lowest_layer: region.lowest_layer,
+                    highest_layer: region.highest_layer,
+                    lowest_pixel: region.lowest_pixel,
+                    volume_mm3: region.volume_mm3,
+                }
+            })
+            .collect();
+        cavities.sort_by_key(|cavity| cavity.lowest_layer);
+        cavities
+    }
+
+    fn find(&mut self, mut label: u32) -> u32 {
+        let mut root = label;
+        while self.parents[root as usize] != root {
+            root = self.parents[root as usize];
+        }
+        while self.parents[label as usize] != root {
+            let next = self.parents[label as usize];
+            self.parents[label as usize] = root;
+            label = next;
+        }
+        root
+    }
+
+    fn union(&mut self, a: u32, b: u32) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        let (ra, rb) = (self.regions[a as usize], self.regions[b as usize]);
+        // Keep the older region as the root so its lowest point wins ties.
+        let (root, child) = if ra.lowest_layer <= rb.lowest_layer { (a, b) } else { (b, a) };
+        self.parents[child as usize] = root;
+        let lower = if ra.lowest_layer <= rb.lowest_layer { ra } else { rb };
+        self.regions[root as usize] = Region {
+            open: ra.open || rb.open,
+            lowest_layer: lower.lowest_layer,
+            highest_layer: ra.highest_layer.max(rb.highest_layer),
+            lowest_pixel: lower.lowest_pixel,
+            volume_mm3: ra.volume_mm3 + rb.volume_mm3,
+        };
+    }
+}
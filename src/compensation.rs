@@ -0,0 +1,132 @@
+//! Corrections applied to rendered layers before they are saved.
+
+use crate::layers::Layer;
+use crate::morphology::{self, DistanceField, Region};
+use crate::{PlateGrid, SliceError, SlicerConfig};
+use image::Luma;
+use serde::Serialize;
+
+/// Growth or shrinkage of every cross-section, correcting parts that come
+/// out off-size from resin shrinkage and light bleed. Positive distances add
+/// material and negative ones remove it.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize)]
+pub struct XyOffset {
+    /// Offset of walls facing the outside of the part, in micrometers.
+    pub outer_um: f32,
+    /// Offset of walls around holes, in micrometers. Light bleed tends to
+    /// close holes up, so this is usually more negative than `outer_um`.
+    pub hole_um: f32,
+}
+
+impl XyOffset {
+    /// The same offset for every wall.
+    pub fn uniform(offset_um: f32) -> Self {
+        Self { outer_um: offset_um, hole_um: offset_um }
+    }
+
+    /// How far walls can move out past the model's footprint, in
+    /// millimeters.
+    pub(crate) fn growth_mm(&self) -> f32 {
+        self.outer_um.max(self.hole_um).max(0.0) / 1000.0
+    }
+}
+
+/// Erosion of the bottom layers, which overexposure makes spread outward.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ElephantFoot {
+    /// How far lit regions are shrunk, in micrometers.
+    pub erosion_um: f32,
+    /// Number of bottom layers shrunk by the full distance.
+    pub layers: u32,
+    /// Number of layers after those over which the erosion fades linearly
+    /// to nothing.
+    pub fade_layers: u32,
+}
+
+impl Default for ElephantFoot {
+    fn default() -> Self {
+        Self {
+            erosion_um: 150.0,
+            layers: 5,
+            fade_layers: 0,
+        }
+    }
+}
+
+impl ElephantFoot {
+    /// Erosion for the layer at `index` in print order.
+    fn erosion_um(&self, index: u32) -> f32 {
+        if index < self.layers {
+            self.erosion_um
+        } else if index - self.layers < self.fade_layers {
+            let step = index - self.layers + 1;
+            self.erosion_um * (1.0 - step as f32 / (self.fade_layers + 1) as f32)
+        } else {
+            0.0
+        }
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    if !config.xy_offset.outer_um.is_finite() || !config.xy_offset.hole_um.is_finite() {
+        return Err(SliceError::InvalidConfig("XY offsets must be finite".to_string()));
+    }
+    if let Some(foot) = config.elephant_foot
+        && (!foot.erosion_um.is_finite() || foot.erosion_um < 0.0)
+    {
+        return Err(SliceError::InvalidConfig(
+            "elephant-foot erosion must be zero or positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Applies every configured correction to a rendered layer.
+pub(crate) fn apply(config: &SlicerConfig, grid: &PlateGrid, layer: &Layer, img: &mut image::GrayImage) {
+    let pitch_mm = (grid.pixel_size_x_mm, grid.pixel_size_y_mm);
+    offset(img, config.xy_offset, pitch_mm);
+    if let Some(foot) = config.elephant_foot {
+        let erosion_um = foot.erosion_um(layer.index);
+        if erosion_um > 0.0 {
+            morphology::erode(img, erosion_um / 1000.0, pitch_mm);
+        }
+    }
+}
+
+/// Moves outer and hole walls by their offsets. Both are measured from the
+/// layer as rendered, so a thin wall between the two cannot be moved twice.
+fn offset(img: &mut image::GrayImage, offset: XyOffset, pitch_mm: (f32, f32)) {
+    if offset.outer_um == offset.hole_um {
+        let radius_mm = offset.outer_um.abs() / 1000.0;
+        if offset.outer_um > 0.0 {
+            morphology::dilate(img, radius_mm, pitch_mm);
+        } else if offset.outer_um < 0.0 {
+            morphology::erode(img, radius_mm, pitch_mm);
+        }
+        return;
+    }
+
+    let (width, height) = img.dimensions();
+    let regions = morphology::regions(img);
+    let region = |x: u32, y: u32| regions[y as usize * width as usize + x as usize];
+    for (wall, offset_um) in [(Region::Outside, offset.outer_um), (Region::Hole, offset.hole_um)] {
+        let radius_mm = offset_um.abs() / 1000.0;
+        if offset_um > 0.0 {
+            // Grow the solid into this region only.
+            let field = DistanceField::new(width, height, false, |x, y| region(x, y) == Region::Solid);
+            field.within(radius_mm, pitch_mm, |x, y| {
+                if region(x, y) == wall {
+                    img.put_pixel(x, y, Luma([255]));
+                }
+            });
+        } else if offset_um < 0.0 {
+            // Let this region eat into the solid.
+            let field = DistanceField::new(width, height, wall == Region::Outside, |x, y| region(x, y) == wall);
+            field.within(radius_mm, pitch_mm, |x, y| {
+                if region(x, y) == Region::Solid {
+                    img.put_pixel(x, y, Luma([0]));
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Planar cross-sections of the mesh as closed polygons, and a scanline
+//! rasterizer that fills them.
+
+use crate::{PlateGrid, Triangle};
+use glam::Vec2;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Rule deciding which regions enclosed by contours are solid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillRule {
+    /// A point is solid if a ray from it crosses an odd number of edges.
+    /// Tolerates inconsistent triangle winding.
+    #[default]
+    EvenOdd,
+    /// A point is solid if the contours wind around it a nonzero number of
+    /// times. Merges overlapping shells but needs consistent normals.
+    NonZero,
+}
+
+/// A closed polygon in plate coordinates (millimeters).
+///
+/// Outer boundaries run counter-clockwise and holes clockwise when the mesh
+/// normals point outward. The last point connects back to the first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub points: Vec<Vec2>,
+}
+
+impl Polygon {
+    /// Shoelace area, positive for counter-clockwise polygons.
+    pub fn signed_area(&self) -> f32 {
+        let n = self.points.len();
+        let twice: f32 = (0..n)
+            .map(|i| self.points[i].perp_dot(self.points[(i + 1) % n]))
+            .sum();
+        twice / 2.0
+    }
+
+    /// Whether this polygon bounds a hole rather than solid material.
+    pub fn is_hole(&self) -> bool {
+        self.signed_area() < 0.0
+    }
+
+    /// Iterates the polygon's edges, including the closing one.
+    pub fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let n = self.points.len();
+        (0..n).map(move |i| (self.points[i], self.points[(i + 1) % n]))
+    }
+}
+
+/// Cuts horizontal cross-sections through a triangle set.
+pub(crate) struct Sectioner<'a> {
+    triangles: &'a [Triangle],
+    /// `(min_z, max_z, index)` sorted by `min_z`.
+    by_min_z: Vec<(f32, f32, usize)>,
+}
+
+impl<'a> Sectioner<'a> {
+    pub(crate) fn new(triangles: &'a [Triangle]) -> Self {
+        let mut by_min_z: Vec<(f32, f32, usize)> = triangles
+            .iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let min_z = t.v0.z.min(t.v1.z).min(t.v2.z);
+                let max_z = t.v0.z.max(t.v1.z).max(t.v2.z);
+                (min_z, max_z, i)
+            })
+            .collect();
+        by_min_z.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { triangles, by_min_z }
+    }
+
+    /// Closed contours of the mesh cut by the plane at height `z`.
+    pub(crate) fn section(&self, z: f32) -> Vec<Polygon> {
+        let end = self.by_min_z.partition_point(|(min_z, _, _)| *min_z <= z);
+        let segments: Vec<(Vec2, Vec2)> = self.by_min_z[..end]
+            .iter()
+            .filter(|(_, max_z, _)| *max_z >= z)
+            .filter_map(|(_, _, i)| triangle_segment(&self.triangles[*i], z))
+            .collect();
+        link_segments(&segments)
+    }
+}
+
+/// The segment where the plane at `z` cuts a triangle, oriented so that the
+/// solid side lies to its left.
+fn triangle_segment(tri: &Triangle, z: f32) -> Option<(Vec2, Vec2)> {
+    let vertices = [tri.v0, tri.v1, tri.v2];
+    let above = vertices.map(|v| v.z >= z);
+    let mut points = Vec::with_capacity(2);
+    for i in 0..3 {
+        let j = (i + 1) % 3;
+        if above[i] != above[j] {
+            points.push(edge_crossing(vertices[i], vertices[j], z));
+        }
+    }
+    let [a, b] = points[..] else {
+        return None;
+    };
+    if a == b {
+        return None;
+    }
+
+    // Outward normals should sit on the right of the direction of travel.
+    let normal = (tri.v1 - tri.v0).cross(tri.v2 - tri.v0);
+    let dir = b - a;
+    if dir.y * normal.x - dir.x * normal.y >= 0.0 {
+        Some((a, b))
+    } else {
+        Some((b, a))
+    }
+}
+
+/// Where the plane at `z` crosses the edge `p`-`q`.
+///
+/// Endpoints are put in a canonical order first so that the two triangles
+/// sharing an edge produce bit-identical points, which lets segments be
+/// joined by exact lookup.
+fn edge_crossing(p: glam::Vec3, q: glam::Vec3, z: f32) -> Vec2 {
+    let (p, q) = if (p.x, p.y, p.z) <= (q.x, q.y, q.z) { (p, q) } else { (q, p) };
+    let t = (z - p.z) / (q.z - p.z);
+    Vec2::new(p.x + t * (q.x - p.x), p.y + t * (q.y - p.y))
+}
+
+fn point_key(p: Vec2) -> (u32, u32) {
+    (p.x.to_bits(), p.y.to_bits())
+}
+
+/// Chains oriented segments end to start into closed polygons.
+fn link_segments(segments: &[(Vec2, Vec2)]) -> Vec<Polygon> {
+    let mut by_start: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+    for (i, (start, _)) in segments.iter().enumerate() {
+        by_start.entry(point_key(*start)).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let mut polygons = Vec::new();
+    for first in 0..segments.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let origin = segments[first].0;
+        let mut points = vec![origin];
+        let mut end = segments[first].1;
+
+        while end != origin {
+            let next = by_start
+                .get(&point_key(end))
+                .and_then(|candidates| candidates.iter().copied().find(|&i| !used[i]));
+            let Some(next) = next else {
+                // Open chain from a non-manifold mesh; close it where it stops.
+                log::debug!("Closing open contour at {:?}", end);
+                break;
+            };
+            used[next] = true;
+            points.push(end);
+            end = segments[next].1;
+        }
+
+        if points.len() >= 3 {
+            polygons.push(Polygon { points });
+        }
+    }
+    polygons
+}
+
+/// Rasterizes `polygons` onto the plate grid, sampling at pixel centers.
+///
+/// Scanlines run up the plate with an active edge table: edges sorted by
+/// their lower end join the table when a scanline reaches it and leave once
+/// one passes their upper end, so each scanline only visits edges it
+/// crosses.
+pub(crate) fn fill(polygons: &[Polygon], grid: &PlateGrid, rule: FillRule) -> image::GrayImage {
+    let mut img = image::GrayImage::new(grid.width_px, grid.height_px);
+    let mut crossings: Vec<(f32, i32)> = Vec::new();
+
+    // Horizontal edges never cross a scanline.
+    let mut edges: Vec<(Vec2, Vec2)> = polygons.iter().flat_map(Polygon::edges).filter(|(a, b)| a.y != b.y).collect();
+    edges.sort_by(|(a0, b0), (a1, b1)| a0.y.min(b0.y).total_cmp(&a1.y.min(b1.y)));
+    let mut waiting = edges.into_iter().peekable();
+    let mut active: Vec<(Vec2, Vec2)> = Vec::new();
+
+    for y in 0..grid.height_px {
+        let py = grid.min_bound.y + (y as f32 + 0.5) * grid.pixel_size_y_mm;
+
+        // Half-open in Y so a vertex on the scanline is counted once.
+        while let Some(edge) = waiting.next_if(|(a, b)| a.y.min(b.y) <= py) {
+            active.push(edge);
+        }
+        active.retain(|(a, b)| py < a.y.max(b.y));
+
+        crossings.clear();
+        for &(a, b) in &active {
+            let winding = if a.y < b.y { 1 } else { -1 };
+            let x = a.x + (py - a.y) * (b.x - a.x) / (b.y - a.y);
+            crossings.push((x, winding));
+        }
+        crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut next = 0;
+        let mut count = 0;
+        let mut winding = 0;
+        for x in 0..grid.width_px {
+            let px = grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm;
+            while next < crossings.len() && crossings[next].0 < px {
+                count += 1;
+                winding += crossings[next].1;
+                next += 1;
+            }
+            let inside = match rule {
+                FillRule::EvenOdd => count % 2 == 1,
+                FillRule::NonZero => winding != 0,
+            };
+            if inside {
+                img.put_pixel(x, grid.height_px - 1 - y, image::Luma([255]));
+            }
+        }
+    }
+    img
+}
@@ -0,0 +1,236 @@
+//! Drain holes letting resin out of hollowed models.
+
+use crate::cavity::{Cavity, CavitySweep, EmptyRegions};
+use crate::layers::Layer;
+use crate::raft::RaftImage;
+use crate::spans::SpanGrid;
+use crate::{PlateGrid, SliceEngine, SliceError, SlicerConfig};
+use glam::{Quat, Vec3};
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// A cylinder removed from the model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct DrainHole {
+    /// Center of the hole's mouth, in model millimeters.
+    pub position_mm: [f32; 3],
+    /// Direction the hole is drilled in from the mouth; need not be unit
+    /// length.
+    pub direction: [f32; 3],
+    pub diameter_um: f32,
+    /// Length of the hole along `direction`, in micrometers.
+    pub depth_um: f32,
+}
+
+/// Drain holes placed automatically at the lowest point of every enclosed
+/// cavity, drilled straight up through the floor beneath it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AutoDrainHoles {
+    pub diameter_um: f32,
+}
+
+impl Default for AutoDrainHoles {
+    fn default() -> Self {
+        Self { diameter_um: 3000.0 }
+    }
+}
+
+/// Parses drain holes written as `X,Y,Z,DX,DY,DZ,DIAMETER_UM,DEPTH_UM` and
+/// separated by semicolons. Blank text means no holes.
+pub fn parse_drain_holes(text: &str) -> Result<Vec<DrainHole>, String> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|hole| !hole.is_empty())
+        .map(|hole| {
+            let values: Vec<f32> = hole
+                .split(',')
+                .map(|value| value.trim().parse::<f32>())
+                .collect::<Result<_, _>>()
+                .map_err(|_| format!("invalid number in drain hole '{}'", hole))?;
+            let [x, y, z, dx, dy, dz, diameter_um, depth_um] = values[..] else {
+                return Err(format!(
+                    "drain hole '{}' should be X,Y,Z,DX,DY,DZ,DIAMETER_UM,DEPTH_UM",
+                    hole
+                ));
+            };
+            Ok(DrainHole {
+                position_mm: [x, y, z],
+                direction: [dx, dy, dz],
+                diameter_um,
+                depth_um,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    for hole in &config.drain_holes {
+        let finite = hole.position_mm.iter().chain(&hole.direction).all(|v| v.is_finite());
+        if !finite || Vec3::from_array(hole.direction) == Vec3::ZERO {
+            return Err(SliceError::InvalidConfig(
+                "drain holes need a finite position and a nonzero direction".to_string(),
+            ));
+        }
+        let sizes = [hole.diameter_um, hole.depth_um];
+        if !sizes.iter().all(|size| size.is_finite() && *size > 0.0) {
+            return Err(SliceError::InvalidConfig(
+                "drain hole diameter and depth must be positive".to_string(),
+            ));
+        }
+    }
+    if let Some(auto) = config.auto_drain_holes {
+        if !auto.diameter_um.is_finite() || auto.diameter_um <= 0.0 {
+            return Err(SliceError::InvalidConfig("drain hole diameter must be positive".to_string()));
+        }
+        if config.engine != SliceEngine::Raycast || config.memory_budget_mb.is_some() {
+            return Err(SliceError::InvalidConfig(
+                "automatic drain holes need the raycast engine without a memory budget".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Moves holes given on the model along with the mesh when it is scaled by
+/// `scale` about `origin`.
+pub(crate) fn scale_holes(holes: &mut [DrainHole], origin: Vec3, scale: Vec3) {
+    for hole in holes {
+        hole.position_mm = (origin + (Vec3::from_array(hole.position_mm) - origin) * scale).to_array();
+        hole.direction = (Vec3::from_array(hole.direction) * scale).to_array();
+    }
+}
+
+/// Turns holes given on the model along with the mesh when it is rotated
+/// about the origin.
+pub(crate) fn rotate_holes(holes: &mut [DrainHole], rotation: Quat) {
+    for hole in holes {
+        hole.position_mm = (rotation * Vec3::from_array(hole.position_mm)).to_array();
+        hole.direction = (rotation * Vec3::from_array(hole.direction)).to_array();
+    }
+}
+
+/// Moves holes given on the model along with the mesh when it is moved by
+/// `offset`.
+pub(crate) fn move_holes(holes: &mut [DrainHole], offset: Vec3) {
+    for hole in holes {
+        hole.position_mm = (Vec3::from_array(hole.position_mm) + offset).to_array();
+    }
+}
+
+/// Calls `clear(x, y)` for every pixel of the layer at height `z` inside one
+/// of the holes, in plate pixel coordinates with row 0 at minimum Y.
+pub(crate) fn punch(holes: &[DrainHole], grid: &PlateGrid, z: f32, mut clear: impl FnMut(u32, u32)) {
+    for hole in holes {
+        let axis = Vec3::from_array(hole.direction).normalize();
+        let start = Vec3::from_array(hole.position_mm);
+        let depth = hole.depth_um / 1000.0;
+        let radius = hole.diameter_um / 2000.0;
+        let end = start + axis * depth;
+        // How far the cylinder's rim reaches beyond its axis along each axis.
+        let rim = (Vec3::ONE - axis * axis).max(Vec3::ZERO).powf(0.5) * radius;
+        let (low, high) = (start.min(end) - rim, start.max(end) + rim);
+        if z < low.z || z > high.z {
+            continue;
+        }
+
+        let column = |v: f32, min: f32, pitch: f32| ((v - min) / pitch - 0.5).clamp(0.0, u32::MAX as f32);
+        let x_range = column(low.x, grid.min_bound.x, grid.pixel_size_x_mm).floor() as u32
+            ..(column(high.x, grid.min_bound.x, grid.pixel_size_x_mm).ceil() as u32 + 1).min(grid.width_px);
+        let y_range = column(low.y, grid.min_bound.y, grid.pixel_size_y_mm).floor() as u32
+            ..(column(high.y, grid.min_bound.y, grid.pixel_size_y_mm).ceil() as u32 + 1).min(grid.height_px);
+        for y in y_range {
+            for x in x_range.clone() {
+                let p = Vec3::new(
+                    grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm,
+                    grid.min_bound.y + (y as f32 + 0.5) * grid.pixel_size_y_mm,
+                    z,
+                );
+                let along = (p - start).dot(axis);
+                if (0.0..=depth).contains(&along) && (p - start - axis * along).length_squared() <= radius * radius {
+                    clear(x, y);
+                }
+            }
+        }
+    }
+}
+
+/// Finds the enclosed cavities of the layers given by `solid`, which says
+/// which pixels of a layer are solid in the same layout as `spans`, raft
+/// included, and places a hole under each. `spans` must cover the whole
+/// plate.
+pub(crate) fn place(
+    auto: AutoDrainHoles,
+    grid: &PlateGrid,
+    spans: &SpanGrid,
+    layers: &[Layer],
+    raft: Option<&RaftImage>,
+    solid: impl Fn(&Layer) -> Vec<bool> + Sync,
+) -> Vec<DrainHole> {
+    let mut sweep = CavitySweep::new();
+    for chunk in layers.chunks(rayon::current_num_threads()) {
+        let labeled: Vec<EmptyRegions> = chunk
+            .par_iter()
+            .map(|layer| EmptyRegions::label(&solid(layer), spans.width(), spans.height()))
+            .collect();
+        for (layer, regions) in chunk.iter().zip(labeled) {
+            sweep.push(regions, grid.voxel_mm3(layer));
+        }
+    }
+    let cavities = sweep.finish();
+    log::info!("Found {} enclosed cavities", cavities.len());
+
+    cavities
+        .iter()
+        .filter_map(|cavity| {
+            let hole = hole_under(auto, grid, spans, layers, raft, cavity);
+            if hole.is_none() {
+                log::warn!(
+                    "A cavity rests on the build plate at Z {:.3} mm; no drain hole placed",
+                    layers[cavity.lowest_layer].z
+                );
+            }
+            hole
+        })
+        .collect()
+}
+
+/// A hole drilled up from the model's underside into the lowest point of a
+/// cavity, reaching one radius past both ends.
+fn hole_under(
+    auto: AutoDrainHoles,
+    grid: &PlateGrid,
+    spans: &SpanGrid,
+    layers: &[Layer],
+    raft: Option<&RaftImage>,
+    cavity: &Cavity,
+) -> Option<DrainHole> {
+    let (x, y) = cavity.lowest_pixel;
+    let z = layers[cavity.lowest_layer].z;
+    // The last span starting below the cavity holds its floor, hollowed or
+    // not. Without one, only the raft can close the cavity off below.
+    let bottom = match spans.pixel(x, y).iter().rev().find(|(enter, _)| *enter <= z) {
+        Some((enter, _)) => *enter,
+        None => raft?.bottom_z(),
+    };
+    let radius_mm = auto.diameter_um / 2000.0;
+    let hole = DrainHole {
+        position_mm: [
+            grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm,
+            grid.min_bound.y + (y as f32 + 0.5) * grid.pixel_size_y_mm,
+            bottom - radius_mm,
+        ],
+        direction: [0.0, 0.0, 1.0],
+        diameter_um: auto.diameter_um,
+        depth_um: (z - bottom + 2.0 * radius_mm) * 1000.0,
+    };
+    log::info!(
+        "Drain hole at ({:.2}, {:.2}) mm under a cavity of {:.1} mm³",
+        hole.position_mm[0],
+        hole.position_mm[1],
+        cavity.volume_mm3
+    );
+    if bottom <= layers[0].z {
+        log::warn!("The drain hole opens onto the build plate, so it only drains once the model is raised");
+    }
+    Some(hole)
+}
@@ -0,0 +1,303 @@
+//! CCITT Group 4 (T.6) compression of bilevel layer images.
+//!
+//! Each row is coded against the row above it using the pass, vertical and
+//! horizontal modes of ITU-T T.6. Solid pixels are coded as "black", so the
+//! output is meant to be tagged `BlackIsZero` to look like the PNG layers.
+
+/// Bit writer filling each byte from its most significant bit.
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    used: u8,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self { bytes: Vec::new(), current: 0, used: 0 }
+    }
+
+    fn push(&mut self, (code, len): (u16, u8)) {
+        for i in (0..len).rev() {
+            self.current = (self.current << 1) | ((code >> i) & 1) as u8;
+            self.used += 1;
+            if self.used == 8 {
+                self.bytes.push(self.current);
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.current << (8 - self.used));
+        }
+        self.bytes
+    }
+}
+
+const PASS: (u16, u8) = (0b0001, 4);
+const HORIZONTAL: (u16, u8) = (0b001, 3);
+/// Vertical mode codes for `a1 - b1` from -3 to 3.
+const VERTICAL: [(u16, u8); 7] = [
+    (0b0000010, 7),
+    (0b000010, 6),
+    (0b010, 3),
+    (0b1, 1),
+    (0b011, 3),
+    (0b000011, 6),
+    (0b0000011, 7),
+];
+const END_OF_BLOCK: (u16, u8) = (0b0000_0000_0001, 12);
+
+/// Encodes an 8-bit image where nonzero pixels are solid.
+pub(crate) fn encode(img: &image::GrayImage) -> Vec<u8> {
+    let width = img.width();
+    let mut out = BitWriter::new();
+    // The row above the first is all white, so has no changing elements.
+    let mut reference: Vec<u32> = Vec::new();
+    let mut coding = Vec::new();
+
+    for row in img.rows() {
+        coding.clear();
+        let mut black = false;
+        for (x, pixel) in row.enumerate() {
+            if (pixel.0[0] != 0) != black {
+                coding.push(x as u32);
+                black = !black;
+            }
+        }
+        encode_row(&mut out, &coding, &reference, width);
+        std::mem::swap(&mut reference, &mut coding);
+    }
+
+    out.push(END_OF_BLOCK);
+    out.push(END_OF_BLOCK);
+    out.finish()
+}
+
+/// Codes one row given the positions where each row changes color. Even
+/// entries start black runs and odd entries start white runs.
+fn encode_row(out: &mut BitWriter, coding: &[u32], reference: &[u32], width: u32) {
+    let at = |changes: &[u32], i: usize| changes.get(i).copied().unwrap_or(width);
+
+    // a0 starts on an imaginary white pixel just left of the row.
+    let mut a0: Option<u32> = None;
+    let mut black = false;
+    loop {
+        let after = |changes: &[u32]| match a0 {
+            Some(a0) => changes.partition_point(|&x| x <= a0),
+            None => 0,
+        };
+        let a1_index = after(coding);
+        let a1 = at(coding, a1_index);
+        // b1 is the next change on the reference row to the color opposite
+        // a0's, i.e. one whose index parity matches a black run when a0 is
+        // white and a white run when a0 is black.
+        let mut b1_index = after(reference);
+        if (b1_index % 2 == 1) != black {
+            b1_index += 1;
+        }
+        let b1 = at(reference, b1_index);
+        let b2 = at(reference, b1_index + 1);
+
+        if b2 < a1 {
+            out.push(PASS);
+            a0 = Some(b2);
+        } else if a1.abs_diff(b1) <= 3 {
+            out.push(VERTICAL[(a1 as i64 - b1 as i64 + 3) as usize]);
+            a0 = Some(a1);
+            black = !black;
+        } else {
+            let a2 = at(coding, a1_index + 1);
+            let start = a0.unwrap_or(0);
+            out.push(HORIZONTAL);
+            push_run(out, a1 - start, black);
+            push_run(out, a2 - a1, !black);
+            a0 = Some(a2);
+        }
+
+        if a0.is_some_and(|a0| a0 >= width) {
+            break;
+        }
+    }
+}
+
+/// Writes a run length as make-up codes followed by a terminating code.
+fn push_run(out: &mut BitWriter, mut run: u32, black: bool) {
+    let (terminating, makeup) = if black {
+        (&BLACK_TERMINATING, &BLACK_MAKEUP)
+    } else {
+        (&WHITE_TERMINATING, &WHITE_MAKEUP)
+    };
+    while run > 2560 {
+        out.push(EXTENDED_MAKEUP[EXTENDED_MAKEUP.len() - 1]);
+        run -= 2560;
+    }
+    if run >= 64 {
+        let index = (run / 64 - 1) as usize;
+        let code = if index < makeup.len() {
+            makeup[index]
+        } else {
+            EXTENDED_MAKEUP[index - makeup.len()]
+        };
+        out.push(code);
+        run %= 64;
+    }
+    out.push(terminating[run as usize]);
+}
+
+const WHITE_TERMINATING: [(u16, u8); 64] = [
+    (0b00110101, 8), (0b000111, 6), (0b0111, 4), (0b1000, 4),
+    (0b1011, 4), (0b1100, 4), (0b1110, 4), (0b1111, 4),
+    (0b10011, 5), (0b10100, 5), (0b00111, 5), (0b01000, 5),
+    (0b001000, 6), (0b000011, 6), (0b110100, 6), (0b110101, 6),
+    (0b101010, 6), (0b101011, 6), (0b0100111, 7), (0b0001100, 7),
+    (0b0001000, 7), (0b0010111, 7), (0b0000011, 7), (0b0000100, 7),
+    (0b0101000, 7), (0b0101011, 7), (0b0010011, 7), (0b0100100, 7),
+    (0b0011000, 7), (0b00000010, 8), (0b00000011, 8), (0b00011010, 8),
+    (0b00011011, 8), (0b00010010, 8), (0b00010011, 8), (0b00010100, 8),
+    (0b00010101, 8), (0b00010110, 8), (0b00010111, 8), (0b00101000, 8),
+    (0b00101001, 8), (0b00101010, 8), (0b00101011, 8), (0b00101100, 8),
+    (0b00101101, 8), (0b00000100, 8), (0b00000101, 8), (0b00001010, 8),
+    (0b00001011, 8), (0b01010010, 8), (0b01010011, 8), (0b01010100, 8),
+    (0b01010101, 8), (0b00100100, 8), (0b00100101, 8), (0b01011000, 8),
+    (0b01011001, 8), (0b01011010, 8), (0b01011011, 8), (0b01001010, 8),
+    (0b01001011, 8), (0b00110010, 8), (0b00110011, 8), (0b00110100, 8),
+];
+
+/// Make-up codes for white runs of 64 to 1728, in steps of 64.
+const WHITE_MAKEUP: [(u16, u8); 27] = [
+    (0b11011, 5), (0b10010, 5), (0b010111, 6), (0b0110111, 7),
+    (0b00110110, 8), (0b00110111, 8), (0b01100100, 8), (0b01100101, 8),
+    (0b01101000, 8), (0b01100111, 8), (0b011001100, 9), (0b011001101, 9),
+    (0b011010010, 9), (0b011010011, 9), (0b011010100, 9), (0b011010101, 9),
+    (0b011010110, 9), (0b011010111, 9), (0b011011000, 9), (0b011011001, 9),
+    (0b011011010, 9), (0b011011011, 9), (0b010011000, 9), (0b010011001, 9),
+    (0b010011010, 9), (0b011000, 6), (0b010011011, 9),
+];
+
+const BLACK_TERMINATING: [(u16, u8); 64] = [
+    (0b0000110111, 10), (0b010, 3), (0b11, 2), (0b10, 2),
+    (0b011, 3), (0b0011, 4), (0b0010, 4), (0b00011, 5),
+    (0b000101, 6), (0b000100, 6), (0b0000100, 7), (0b0000101, 7),
+    (0b0000111, 7), (0b00000100, 8), (0b00000111, 8), (0b000011000, 9),
+    (0b0000010111, 10), (0b0000011000, 10), (0b0000001000, 10), (0b00001100111, 11),
+    (0b00001101000, 11), (0b00001101100, 11), (0b00000110111, 11), (0b00000101000, 11),
+    (0b00000010111, 11), (0b00000011000, 11), (0b000011001010, 12), (0b000011001011, 12),
+    (0b000011001100, 12), (0b000011001101, 12), (0b000001101000, 12), (0b000001101001, 12),
+    (0b000001101010, 12), (0b000001101011, 12), (0b000011010010, 12), (0b000011010011, 12),
+    (0b000011010100, 12), (0b000011010101, 12), (0b000011010110, 12), (0b000011010111, 12),
+    (0b000001101100, 12), (0b000001101101, 12), (0b000011011010, 12), (0b000011011011, 12),
+    (0b000001010100, 12), (0b000001010101, 12), (0b000001010110, 12), (0b000001010111, 12),
+    (0b000001100100, 12), (0b000001100101, 12), (0b000001010010, 12), (0b000001010011, 12),
+    (0b000000100100, 12), (0b000000110111, 12), (0b000000111000, 12), (0b000000100111, 12),
+    (0b000000101000, 12), (0b000001011000, 12), (0b000001011001, 12), (0b000000101011, 12),
+    (0b000000101100, 12), (0b000001011010, 12), (0b000001100110, 12), (0b000001100111, 12),
+];
+
+/// Make-up codes for black runs of 64 to 1728, in steps of 64.
+const BLACK_MAKEUP: [(u16, u8); 27] = [
+    (0b0000001111, 10), (0b000011001000, 12), (0b000011001001, 12), (0b000001011011, 12),
+    (0b000000110011, 12), (0b000000110100, 12), (0b000000110101, 12), (0b0000001101100, 13),
+    (0b0000001101101, 13), (0b0000001001010, 13), (0b0000001001011, 13), (0b0000001001100, 13),
+    (0b0000001001101, 13), (0b0000001110010, 13), (0b0000001110011, 13), (0b0000001110100, 13),
+    (0b0000001110101, 13), (0b0000001110110, 13), (0b0000001110111, 13), (0b0000001010010, 13),
+    (0b0000001010011, 13), (0b0000001010100, 13), (0b0000001010101, 13), (0b0000001011010, 13),
+    (0b0000001011011, 13), (0b0000001100100, 13), (0b0000001100101, 13),
+];
+
+/// Make-up codes shared by both colors for runs of 1792 to 2560.
+const EXTENDED_MAKEUP: [(u16, u8); 13] = [
+    (0b00000001000, 11), (0b00000001100, 11), (0b00000001101, 11), (0b000000010010, 12),
+    (0b000000010011, 12), (0b000000010100, 12), (0b000000010101, 12), (0b000000010110, 12),
+    (0b000000010111, 12), (0b000000011100, 12), (0b000000011101, 12), (0b000000011110, 12),
+    (0b000000011111, 12),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fax::Color;
+
+    /// Builds a row of alternating white and black runs, starting white.
+    fn row(runs: &[u32]) -> Vec<bool> {
+        let mut black = false;
+        let mut pixels = Vec::new();
+        for &run in runs {
+            pixels.extend(std::iter::repeat_n(black, run as usize));
+            black = !black;
+        }
+        pixels
+    }
+
+    fn image(rows: &[Vec<bool>]) -> image::GrayImage {
+        image::GrayImage::from_fn(rows[0].len() as u32, rows.len() as u32, |x, y| {
+            image::Luma([if rows[y as usize][x as usize] { 255 } else { 0 }])
+        })
+    }
+
+    /// Encodes the rows and checks a reference decoder reads them back.
+    fn assert_round_trip(rows: &[Vec<bool>]) {
+        let width = rows[0].len() as u16;
+        let mut decoded = Vec::new();
+        fax::decoder::decode_g4(encode(&image(rows)).into_iter(), width, None, |changes| {
+            decoded.push(fax::decoder::pels(changes, width).map(|c| c == Color::Black).collect::<Vec<_>>());
+        })
+        .expect("decoder rejected the encoded image");
+        assert_eq!(decoded.len(), rows.len(), "decoded a different number of rows");
+        for (y, (got, want)) in decoded.iter().zip(rows).enumerate() {
+            assert!(got == want, "row {} decodes differently", y);
+        }
+    }
+
+    #[test]
+    fn encodes_a_white_pixel_as_one_vertical_code() {
+        // V0, then two end-of-block codes, padded to whole bytes.
+        assert_eq!(encode(&image(&[row(&[1])])), [0x80, 0x08, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn round_trips_solid_rows() {
+        assert_round_trip(&[row(&[1728])]);
+        assert_round_trip(&[row(&[0, 1728])]);
+        assert_round_trip(&[row(&[0, 1728]), row(&[1728]), row(&[0, 1728])]);
+    }
+
+    #[test]
+    fn round_trips_runs_at_makeup_boundaries() {
+        for run in [63, 64, 65, 127, 128, 1727, 1728, 1729, 1791, 1792, 2560] {
+            let width = run + 130;
+            // The same run in white and in black, in horizontal mode.
+            assert_round_trip(&[row(&[run, 2, 128])]);
+            assert_round_trip(&[row(&[0, run, width - run])]);
+            assert_round_trip(&[row(&[2, run, width - run - 2])]);
+        }
+    }
+
+    #[test]
+    fn round_trips_runs_longer_than_the_makeup_tables() {
+        for run in [2561, 5120, 5121, 6000] {
+            assert_round_trip(&[row(&[run, 7])]);
+            assert_round_trip(&[row(&[0, run, 7])]);
+            assert_round_trip(&[row(&[0, run])]);
+        }
+    }
+
+    #[test]
+    fn round_trips_rows_coded_against_the_row_above() {
+        // Edges that stay put, shift by up to three pixels, jump past the
+        // reference edges and vanish exercise the vertical, pass and
+        // horizontal modes.
+        assert_round_trip(&[
+            row(&[10, 20, 30, 40]),
+            row(&[10, 20, 30, 40]),
+            row(&[13, 17, 33, 37]),
+            row(&[10, 23, 27, 40]),
+            row(&[60, 40]),
+            row(&[5, 2, 50, 2, 41]),
+            row(&[100]),
+            row(&[0, 100]),
+        ]);
+    }
+}
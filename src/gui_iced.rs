@@ -1,6 +1,6 @@
-use iced::widget::{button, checkbox, column, container, progress_bar, row, text, text_input};
+use iced::widget::{button, checkbox, column, container, pick_list, progress_bar, row, text, text_input};
 use iced::{Alignment, Element, Length, Subscription, Task, Theme};
-use rs_licer::{slice_with_progress, SlicerConfig};
+use rs_licer::{AreaProfile, LayerArea, AutoOrient, parse_drain_holes, IslandCheck, parse_layer_ranges, slice_with_progress, AdaptiveLayers, AutoDrainHoles, ElephantFoot, Hollowing, Crenelation, Raft, SupportStyle, Supports, Infill, InfillPattern, Orientation, ResinStore, Rotation, XyOffset, ProgressEvent, SliceEngine, SlicerConfig, SvgOutput, LayerFormat, PngCompression, DEFAULT_FILE_NAME_TEMPLATE};
 use std::sync::mpsc::{channel, Receiver};
 use std::time::{Duration, Instant};
 
@@ -16,31 +16,146 @@ pub enum Message {
     InputPathChanged(String),
     OutputDirChanged(String),
     PixelSizeChanged(String),
+    PixelSizeYChanged(String),
     LayerHeightChanged(String),
+    MemoryBudgetChanged(String),
+    LayerRangesChanged(String),
+    FileNameTemplateChanged(String),
+    AdaptiveToggled(bool),
+    MinLayerHeightChanged(String),
+    MaxLayerHeightChanged(String),
+    MaxCuspChanged(String),
+    XyOffsetChanged(String),
+    HoleOffsetChanged(String),
+    ElephantFootToggled(bool),
+    ElephantFootErosionChanged(String),
+    ElephantFootLayersChanged(String),
+    ElephantFootFadeChanged(String),
+    ResinSelected(String),
+    AutoOrientToggled(bool),
+    SupportsToggled(bool),
+    SupportStyleSelected(SupportStyle),
+    SupportTipChanged(String),
+    SupportPillarChanged(String),
+    SupportBaseChanged(String),
+    SupportElevationChanged(String),
+    RaftToggled(bool),
+    RaftThicknessChanged(String),
+    RaftMarginChanged(String),
+    RaftChamferChanged(String),
+    RaftCrenelationToggled(bool),
+    HollowToggled(bool),
+    WallThicknessChanged(String),
+    InfillToggled(bool),
+    InfillPatternSelected(InfillPattern),
+    InfillDensityChanged(String),
+    InfillCellChanged(String),
+    DrainHolesChanged(String),
+    AutoDrainHolesToggled(bool),
+    DrainDiameterChanged(String),
+    EngineSelected(SliceEngine),
+    SvgOutputSelected(SvgOutput),
+    LayerFormatSelected(LayerFormat),
+    PngCompressionSelected(PngCompression),
+    MirrorXToggled(bool),
+    MirrorYToggled(bool),
+    RotationSelected(Rotation),
     ZeroSliceToggled(bool),
     DeleteBelowZeroToggled(bool),
     DeleteOutputDirToggled(bool),
     OpenOutputDirToggled(bool),
+    CavityCheckToggled(bool),
+    IslandCheckToggled(bool),
+    IslandOverlaysToggled(bool),
+    StrictToggled(bool),
+    AreaProfileToggled(bool),
+    AreaJumpChanged(String),
     BrowseFile,
     BrowseOutputDir,
     Slice,
     Tick,
 }
 
+/// Warnings listed under the status before the rest are left to the manifest.
+const MAX_WARNINGS: usize = 10;
+
+/// Size of the area profile chart in pixels.
+const CHART_WIDTH: u32 = 600;
+const CHART_HEIGHT: u32 = 120;
+
+/// Resin choice that leaves the model unscaled.
+const NO_RESIN: &str = "None";
+
 pub struct SlicerApp {
     input_path: String,
     output_dir: String,
     pixel_size: String,
+    pixel_size_y: String,
     layer_height: String,
+    memory_budget: String,
+    layer_ranges: String,
+    file_name_template: String,
+    adaptive: bool,
+    min_layer_height: String,
+    max_layer_height: String,
+    max_cusp: String,
+    xy_offset: String,
+    hole_offset: String,
+    elephant_foot: bool,
+    elephant_foot_erosion: String,
+    elephant_foot_layers: String,
+    elephant_foot_fade: String,
+    auto_orient: bool,
+    supports: bool,
+    support_style: SupportStyle,
+    support_tip: String,
+    support_pillar: String,
+    support_base: String,
+    support_elevation: String,
+    raft: bool,
+    raft_thickness: String,
+    raft_margin: String,
+    raft_chamfer: String,
+    raft_crenelation: bool,
+    hollow: bool,
+    wall_thickness: String,
+    infill: bool,
+    infill_pattern: InfillPattern,
+    infill_density: String,
+    infill_cell: String,
+    drain_holes: String,
+    auto_drain_holes: bool,
+    drain_diameter: String,
+    resins: ResinStore,
+    /// Name of the selected resin profile, or [`NO_RESIN`].
+    resin: String,
+    engine: SliceEngine,
+    svg_output: SvgOutput,
+    layer_format: LayerFormat,
+    png_compression: PngCompression,
+    orientation: Orientation,
     zero_slice_position: bool,
     delete_below_zero: bool,
     delete_output_dir: bool,
     open_output_dir: bool,
+    cavity_check: bool,
+    island_check: bool,
+    islands: IslandCheck,
+    area_profile: bool,
+    area_jump: String,
     is_processing: bool,
     progress: f32,
+    phase_progress: Option<f32>,
     status_message: String,
-    progress_rx: Option<Receiver<(f32, String)>>,
-    start_time: Option<Instant>,
+    /// Warnings from the last finished job.
+    warnings: Vec<String>,
+    /// Orientations ranked by auto-orient in the last finished job.
+    orientations: Vec<String>,
+    /// Cross-section area against height from the last finished job, and
+    /// the caption under it.
+    area_chart: Option<(iced::widget::image::Handle, String)>,
+    progress_rx: Option<Receiver<ProgressEvent>>,
+    phase_start: Option<(std::mem::Discriminant<ProgressEvent>, Instant)>,
     estimated_time: Option<String>,
 }
 
@@ -50,16 +165,67 @@ impl Default for SlicerApp {
             input_path: String::new(),
             output_dir: "slices".to_string(),
             pixel_size: "33.3333".to_string(),
+            pixel_size_y: String::new(),
             layer_height: "20.0".to_string(),
+            memory_budget: String::new(),
+            layer_ranges: String::new(),
+            file_name_template: DEFAULT_FILE_NAME_TEMPLATE.to_string(),
+            adaptive: false,
+            min_layer_height: "10.0".to_string(),
+            max_layer_height: "100.0".to_string(),
+            max_cusp: "20.0".to_string(),
+            xy_offset: "0.0".to_string(),
+            hole_offset: String::new(),
+            elephant_foot: false,
+            elephant_foot_erosion: "150.0".to_string(),
+            elephant_foot_layers: "5".to_string(),
+            elephant_foot_fade: "0".to_string(),
+            auto_orient: false,
+            supports: false,
+            support_style: SupportStyle::Pillar,
+            support_tip: "400.0".to_string(),
+            support_pillar: "1000.0".to_string(),
+            support_base: "3000.0".to_string(),
+            support_elevation: "5000.0".to_string(),
+            raft: false,
+            raft_thickness: "1000.0".to_string(),
+            raft_margin: "2000.0".to_string(),
+            raft_chamfer: "1000.0".to_string(),
+            raft_crenelation: false,
+            hollow: false,
+            wall_thickness: "2000.0".to_string(),
+            infill: false,
+            infill_pattern: InfillPattern::Grid,
+            infill_density: "10.0".to_string(),
+            infill_cell: "5000.0".to_string(),
+            drain_holes: String::new(),
+            auto_drain_holes: false,
+            drain_diameter: "3000.0".to_string(),
+            resins: ResinStore::load_default(),
+            resin: NO_RESIN.to_string(),
+            engine: SliceEngine::Raycast,
+            svg_output: SvgOutput::Off,
+            layer_format: LayerFormat::Png,
+            png_compression: PngCompression::Balanced,
+            orientation: Orientation::default(),
             zero_slice_position: false,
             delete_below_zero: false,
             delete_output_dir: true,
             open_output_dir: true,
+            cavity_check: false,
+            island_check: false,
+            islands: IslandCheck::default(),
+            area_profile: false,
+            area_jump: "100.0".to_string(),
             is_processing: false,
             progress: 0.0,
+            phase_progress: None,
             status_message: "Ready to slice".to_string(),
+            warnings: Vec::new(),
+            orientations: Vec::new(),
+            area_chart: None,
             progress_rx: None,
-            start_time: None,
+            phase_start: None,
             estimated_time: None,
         }
     }
@@ -80,10 +246,182 @@ impl SlicerApp {
                 self.pixel_size = value;
                 Task::none()
             }
+            Message::PixelSizeYChanged(value) => {
+                self.pixel_size_y = value;
+                Task::none()
+            }
             Message::LayerHeightChanged(value) => {
                 self.layer_height = value;
                 Task::none()
             }
+            Message::MemoryBudgetChanged(value) => {
+                self.memory_budget = value;
+                Task::none()
+            }
+            Message::LayerRangesChanged(value) => {
+                self.layer_ranges = value;
+                Task::none()
+            }
+            Message::FileNameTemplateChanged(value) => {
+                self.file_name_template = value;
+                Task::none()
+            }
+            Message::AdaptiveToggled(value) => {
+                self.adaptive = value;
+                Task::none()
+            }
+            Message::MinLayerHeightChanged(value) => {
+                self.min_layer_height = value;
+                Task::none()
+            }
+            Message::MaxLayerHeightChanged(value) => {
+                self.max_layer_height = value;
+                Task::none()
+            }
+            Message::MaxCuspChanged(value) => {
+                self.max_cusp = value;
+                Task::none()
+            }
+            Message::XyOffsetChanged(value) => {
+                self.xy_offset = value;
+                Task::none()
+            }
+            Message::HoleOffsetChanged(value) => {
+                self.hole_offset = value;
+                Task::none()
+            }
+            Message::ElephantFootToggled(value) => {
+                self.elephant_foot = value;
+                Task::none()
+            }
+            Message::ElephantFootErosionChanged(value) => {
+                self.elephant_foot_erosion = value;
+                Task::none()
+            }
+            Message::ElephantFootLayersChanged(value) => {
+                self.elephant_foot_layers = value;
+                Task::none()
+            }
+            Message::ElephantFootFadeChanged(value) => {
+                self.elephant_foot_fade = value;
+                Task::none()
+            }
+            Message::AutoOrientToggled(value) => {
+                self.auto_orient = value;
+                Task::none()
+            }
+            Message::SupportsToggled(value) => {
+                self.supports = value;
+                Task::none()
+            }
+            Message::SupportStyleSelected(style) => {
+                self.support_style = style;
+                Task::none()
+            }
+            Message::SupportTipChanged(value) => {
+                self.support_tip = value;
+                Task::none()
+            }
+            Message::SupportPillarChanged(value) => {
+                self.support_pillar = value;
+                Task::none()
+            }
+            Message::SupportBaseChanged(value) => {
+                self.support_base = value;
+                Task::none()
+            }
+            Message::SupportElevationChanged(value) => {
+                self.support_elevation = value;
+                Task::none()
+            }
+            Message::RaftToggled(value) => {
+                self.raft = value;
+                Task::none()
+            }
+            Message::RaftThicknessChanged(value) => {
+                self.raft_thickness = value;
+                Task::none()
+            }
+            Message::RaftMarginChanged(value) => {
+                self.raft_margin = value;
+                Task::none()
+            }
+            Message::RaftChamferChanged(value) => {
+                self.raft_chamfer = value;
+                Task::none()
+            }
+            Message::RaftCrenelationToggled(value) => {
+                self.raft_crenelation = value;
+                Task::none()
+            }
+            Message::HollowToggled(value) => {
+                self.hollow = value;
+                Task::none()
+            }
+            Message::WallThicknessChanged(value) => {
+                self.wall_thickness = value;
+                Task::none()
+            }
+            Message::InfillToggled(value) => {
+                self.infill = value;
+                Task::none()
+            }
+            Message::InfillPatternSelected(pattern) => {
+                self.infill_pattern = pattern;
+                Task::none()
+            }
+            Message::InfillDensityChanged(value) => {
+                self.infill_density = value;
+                Task::none()
+            }
+            Message::InfillCellChanged(value) => {
+                self.infill_cell = value;
+                Task::none()
+            }
+            Message::DrainHolesChanged(value) => {
+                self.drain_holes = value;
+                Task::none()
+            }
+            Message::AutoDrainHolesToggled(value) => {
+                self.auto_drain_holes = value;
+                Task::none()
+            }
+            Message::DrainDiameterChanged(value) => {
+                self.drain_diameter = value;
+                Task::none()
+            }
+            Message::ResinSelected(name) => {
+                self.resin = name;
+                Task::none()
+            }
+            Message::EngineSelected(engine) => {
+                self.engine = engine;
+                Task::none()
+            }
+            Message::SvgOutputSelected(svg_output) => {
+                self.svg_output = svg_output;
+                Task::none()
+            }
+            Message::LayerFormatSelected(layer_format) => {
+                self.layer_format = layer_format;
+                Task::none()
+            }
+            Message::PngCompressionSelected(png_compression) => {
+                self.png_compression = png_compression;
+                Task::none()
+            }
+            Message::MirrorXToggled(value) => {
+                self.orientation.mirror_x = value;
+                Task::none()
+            }
+            Message::MirrorYToggled(value) => {
+                self.orientation.mirror_y = value;
+                Task::none()
+            }
+            Message::RotationSelected(rotation) => {
+                self.orientation.rotation = rotation;
+                Task::none()
+            }
             Message::ZeroSliceToggled(value) => {
                 self.zero_slice_position = value;
                 Task::none()
@@ -100,6 +438,30 @@ impl SlicerApp {
                 self.open_output_dir = value;
                 Task::none()
             }
+            Message::CavityCheckToggled(value) => {
+                self.cavity_check = value;
+                Task::none()
+            }
+            Message::IslandCheckToggled(value) => {
+                self.island_check = value;
+                Task::none()
+            }
+            Message::IslandOverlaysToggled(value) => {
+                self.islands.overlays = value;
+                Task::none()
+            }
+            Message::StrictToggled(value) => {
+                self.islands.strict = value;
+                Task::none()
+            }
+            Message::AreaProfileToggled(value) => {
+                self.area_profile = value;
+                Task::none()
+            }
+            Message::AreaJumpChanged(value) => {
+                self.area_jump = value;
+                Task::none()
+            }
             Message::BrowseFile => {
                 if let Some(path) = rfd::FileDialog::new()
                     .add_filter("STL Files", &["stl"])
@@ -124,29 +486,132 @@ impl SlicerApp {
                 }
 
                 let pixel_size = self.pixel_size.parse::<f32>().unwrap_or(33.3333);
+                let pixel_size_y = self.pixel_size_y.trim().parse::<f32>().ok();
                 let layer_height = self.layer_height.parse::<f32>().unwrap_or(20.0);
+                let memory_budget_mb = self.memory_budget.trim().parse::<u32>().ok();
+                let layer_ranges = match parse_layer_ranges(&self.layer_ranges) {
+                    Ok(ranges) => ranges,
+                    Err(e) => {
+                        self.status_message = e;
+                        return Task::none();
+                    }
+                };
+                let drain_holes = match parse_drain_holes(&self.drain_holes) {
+                    Ok(holes) => holes,
+                    Err(e) => {
+                        self.status_message = e;
+                        return Task::none();
+                    }
+                };
+                let auto_drain_holes = self.auto_drain_holes.then(|| AutoDrainHoles {
+                    diameter_um: self
+                        .drain_diameter
+                        .parse()
+                        .unwrap_or(AutoDrainHoles::default().diameter_um),
+                });
+                let defaults = AdaptiveLayers::default();
+                let adaptive_layers = self.adaptive.then(|| AdaptiveLayers {
+                    min_height_um: self.min_layer_height.parse().unwrap_or(defaults.min_height_um),
+                    max_height_um: self.max_layer_height.parse().unwrap_or(defaults.max_height_um),
+                    max_cusp_um: self.max_cusp.parse().unwrap_or(defaults.max_cusp_um),
+                });
+                let xy_offset_um = self.xy_offset.trim().parse().unwrap_or(0.0);
+                let xy_offset = XyOffset {
+                    outer_um: xy_offset_um,
+                    hole_um: self.hole_offset.trim().parse().unwrap_or(xy_offset_um),
+                };
+                let defaults = ElephantFoot::default();
+                let elephant_foot = self.elephant_foot.then(|| ElephantFoot {
+                    erosion_um: self.elephant_foot_erosion.parse().unwrap_or(defaults.erosion_um),
+                    layers: self.elephant_foot_layers.parse().unwrap_or(defaults.layers),
+                    fade_layers: self.elephant_foot_fade.parse().unwrap_or(defaults.fade_layers),
+                });
+                let defaults = Infill::default();
+                let infill = self.infill.then(|| Infill {
+                    pattern: self.infill_pattern,
+                    density_pct: self.infill_density.parse().unwrap_or(defaults.density_pct),
+                    cell_size_um: self.infill_cell.parse().unwrap_or(defaults.cell_size_um),
+                });
+                let defaults = Supports::default();
+                let supports = self.supports.then(|| Supports {
+                    style: self.support_style,
+                    tip_diameter_um: self.support_tip.parse().unwrap_or(defaults.tip_diameter_um),
+                    pillar_diameter_um: self.support_pillar.parse().unwrap_or(defaults.pillar_diameter_um),
+                    base_diameter_um: self.support_base.parse().unwrap_or(defaults.base_diameter_um),
+                    elevation_um: self.support_elevation.parse().unwrap_or(defaults.elevation_um),
+                    ..defaults
+                });
+                let defaults = Raft::default();
+                let raft = self.raft.then(|| Raft {
+                    thickness_um: self.raft_thickness.parse().unwrap_or(defaults.thickness_um),
+                    margin_um: self.raft_margin.parse().unwrap_or(defaults.margin_um),
+                    chamfer_um: self.raft_chamfer.parse().unwrap_or(defaults.chamfer_um),
+                    crenelation: self.raft_crenelation.then(Crenelation::default),
+                });
+                let hollowing = self.hollow.then(|| Hollowing {
+                    wall_thickness_um: self
+                        .wall_thickness
+                        .parse()
+                        .unwrap_or(Hollowing::default().wall_thickness_um),
+                    infill,
+                });
 
                 let config = SlicerConfig {
                     input_path: self.input_path.clone(),
                     output_dir: self.output_dir.clone(),
                     pixel_size_um: pixel_size,
+                    pixel_size_y_um: pixel_size_y,
                     layer_height_um: layer_height,
                     zero_slice_position: self.zero_slice_position,
                     delete_below_zero: self.delete_below_zero,
                     delete_output_dir: self.delete_output_dir,
                     open_output_dir: self.open_output_dir,
+                    cavity_check: self.cavity_check,
+                    islands: self.island_check.then_some(self.islands),
+                    area_profile: self.area_profile.then(|| AreaProfile {
+                        jump_threshold_mm2: self
+                            .area_jump
+                            .parse()
+                            .unwrap_or(AreaProfile::default().jump_threshold_mm2),
+                    }),
+                    memory_budget_mb,
+                    engine: self.engine,
+                    svg_output: self.svg_output,
+                    layer_format: self.layer_format,
+                    png_compression: self.png_compression,
+                    orientation: self.orientation,
+                    resin: self.resins.get(&self.resin).cloned(),
+                    auto_orient: self.auto_orient.then(AutoOrient::default),
+                    supports,
+                    raft,
+                    hollowing,
+                    drain_holes,
+                    auto_drain_holes,
+                    xy_offset,
+                    elephant_foot,
+                    adaptive_layers,
+                    layer_ranges,
+                    file_name_template: match self.file_name_template.trim() {
+                        "" => DEFAULT_FILE_NAME_TEMPLATE.to_string(),
+                        template => template.to_string(),
+                    },
+                    ..Default::default()
                 };
 
                 self.is_processing = true;
                 self.progress = 0.0;
+                self.phase_progress = None;
                 self.status_message = "Starting...".to_string();
-                self.start_time = Some(Instant::now());
+                self.warnings.clear();
+                self.orientations.clear();
+                self.area_chart = None;
+                self.phase_start = None;
 
                 let (tx, rx) = channel();
                 self.progress_rx = Some(rx);
 
                 std::thread::spawn(move || {
-                    slice_with_progress(config, Some(tx));
+                    let _ = slice_with_progress(config, &tx);
                 });
 
                 Task::none()
@@ -155,30 +620,73 @@ impl SlicerApp {
                 let mut should_finish = false;
                 
                 if let Some(ref rx) = self.progress_rx {
-                    while let Ok((progress, message)) = rx.try_recv() {
-                        self.progress = progress;
-                        self.status_message = message;
+                    while let Ok(event) = rx.try_recv() {
+                        self.progress = event.overall_fraction();
+                        self.phase_progress = event.phase_fraction();
+                        self.status_message = event.to_string();
 
-                        if let Some(start) = self.start_time {
-                            if progress > 0.0 && progress < 1.0 {
-                                let elapsed = start.elapsed().as_secs_f32();
-                                let total_estimated = elapsed / progress;
-                                let remaining = total_estimated - elapsed;
+                        // Estimate from the current phase only, since phases
+                        // run at very different rates.
+                        let phase = std::mem::discriminant(&event);
+                        let phase_start = match self.phase_start {
+                            Some((current, start)) if current == phase => start,
+                            _ => {
+                                let now = Instant::now();
+                                self.phase_start = Some((phase, now));
+                                now
+                            }
+                        };
+
+                        self.estimated_time = match self.phase_progress {
+                            Some(fraction) if fraction > 0.0 && fraction < 1.0 => {
+                                let elapsed = phase_start.elapsed().as_secs_f32();
+                                let remaining = elapsed / fraction - elapsed;
 
                                 let mins = (remaining / 60.0) as u32;
                                 let secs = (remaining % 60.0) as u32;
-                                self.estimated_time = Some(format!("{}m {}s", mins, secs));
-                            } else if progress >= 1.0 {
-                                should_finish = true;
+                                Some(format!("{}m {}s", mins, secs))
+                            }
+                            _ => None,
+                        };
+
+                        if let ProgressEvent::Finished(report) = &event {
+                            self.warnings = report
+                                .cavities
+                                .iter()
+                                .map(|cavity| cavity.to_string())
+                                .chain(report.islands.iter().map(|island| island.to_string()))
+                                .chain(
+                                    report
+                                        .area_profile
+                                        .iter()
+                                        .filter(|layer| layer.jump)
+                                        .map(|layer| layer.to_string()),
+                                )
+                                .collect();
+                            self.area_chart = area_chart(&report.area_profile);
+                            self.orientations = report
+                                .orientations
+                                .iter()
+                                .enumerate()
+                                .map(|(rank, candidate)| format!("{}. {}", rank + 1, candidate))
+                                .collect();
+                            if self.warnings.len() > MAX_WARNINGS {
+                                let more = self.warnings.len() - MAX_WARNINGS;
+                                self.warnings.truncate(MAX_WARNINGS);
+                                self.warnings.push(format!("{} more, see manifest.json", more));
                             }
                         }
+                        if event.is_terminal() {
+                            should_finish = true;
+                        }
                     }
                 }
                 
                 if should_finish {
                     self.is_processing = false;
                     self.progress_rx = None;
-                    self.start_time = None;
+                    self.phase_start = None;
+                    self.phase_progress = None;
                     self.estimated_time = None;
                 }
                 
@@ -227,11 +735,24 @@ impl SlicerApp {
         .spacing(10)
         .align_y(Alignment::Center);
 
+        let file_name_row = row![
+            text("File Name:").width(Length::Fixed(120.0)),
+            text_input("e.g. {model}_{index:05}, {z_um}, {z_mm:.3}", &self.file_name_template)
+                .on_input(Message::FileNameTemplateChanged)
+                .width(Length::Fill),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
         let pixel_row = row![
             text("Pixel Size (μm):").width(Length::Fixed(120.0)),
             text_input("33.3333", &self.pixel_size)
                 .on_input(Message::PixelSizeChanged)
                 .width(Length::Fill),
+            text("Y:"),
+            text_input("Same as X", &self.pixel_size_y)
+                .on_input(Message::PixelSizeYChanged)
+                .width(Length::Fill),
         ]
         .spacing(10)
         .align_y(Alignment::Center);
@@ -245,6 +766,217 @@ impl SlicerApp {
         .spacing(10)
         .align_y(Alignment::Center);
 
+        let ranges_row = row![
+            text("Layer Ranges:").width(Length::Fixed(120.0)),
+            text_input("e.g. 0-5:50, 5-20:20 (mm-mm:μm)", &self.layer_ranges)
+                .on_input(Message::LayerRangesChanged)
+                .width(Length::Fill),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let adaptive_row = row![
+            checkbox("Adaptive Layer Height", self.adaptive)
+                .on_toggle(Message::AdaptiveToggled),
+        ];
+
+        let adaptive_settings = row![
+            text("Min (μm):"),
+            text_input("10.0", &self.min_layer_height)
+                .on_input(Message::MinLayerHeightChanged),
+            text("Max (μm):"),
+            text_input("100.0", &self.max_layer_height)
+                .on_input(Message::MaxLayerHeightChanged),
+            text("Max Cusp (μm):"),
+            text_input("20.0", &self.max_cusp)
+                .on_input(Message::MaxCuspChanged),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let offset_row = row![
+            text("XY Offset (μm):").width(Length::Fixed(120.0)),
+            text_input("0.0", &self.xy_offset)
+                .on_input(Message::XyOffsetChanged),
+            text("Hole Offset (μm):"),
+            text_input("Same as XY offset", &self.hole_offset)
+                .on_input(Message::HoleOffsetChanged),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let elephant_foot_row = row![
+            checkbox("Elephant-Foot Compensation", self.elephant_foot)
+                .on_toggle(Message::ElephantFootToggled),
+        ];
+
+        let elephant_foot_settings = row![
+            text("Erosion (μm):"),
+            text_input("150.0", &self.elephant_foot_erosion)
+                .on_input(Message::ElephantFootErosionChanged),
+            text("Layers:"),
+            text_input("5", &self.elephant_foot_layers)
+                .on_input(Message::ElephantFootLayersChanged),
+            text("Fade Layers:"),
+            text_input("0", &self.elephant_foot_fade)
+                .on_input(Message::ElephantFootFadeChanged),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let resin_names: Vec<String> = std::iter::once(NO_RESIN.to_string())
+            .chain(self.resins.profiles.iter().map(|resin| resin.name.clone()))
+            .collect();
+        let resin_row = row![
+            text("Resin:").width(Length::Fixed(120.0)),
+            pick_list(resin_names, Some(self.resin.clone()), Message::ResinSelected),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let supports_row = row![
+            checkbox("Auto Orient", self.auto_orient)
+                .on_toggle(Message::AutoOrientToggled),
+            checkbox("Supports", self.supports)
+                .on_toggle(Message::SupportsToggled),
+        ]
+        .spacing(20);
+
+        let supports_settings = row![
+            pick_list(SupportStyle::ALL, Some(self.support_style), Message::SupportStyleSelected),
+            text("Tip (μm):"),
+            text_input("400.0", &self.support_tip)
+                .on_input(Message::SupportTipChanged),
+            text("Pillar (μm):"),
+            text_input("1000.0", &self.support_pillar)
+                .on_input(Message::SupportPillarChanged),
+            text("Base (μm):"),
+            text_input("3000.0", &self.support_base)
+                .on_input(Message::SupportBaseChanged),
+            text("Lift (μm):"),
+            text_input("5000.0", &self.support_elevation)
+                .on_input(Message::SupportElevationChanged),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let raft_row = row![
+            checkbox("Raft", self.raft)
+                .on_toggle(Message::RaftToggled),
+        ];
+
+        let raft_settings = row![
+            text("Thickness (μm):"),
+            text_input("1000.0", &self.raft_thickness)
+                .on_input(Message::RaftThicknessChanged),
+            text("Margin (μm):"),
+            text_input("2000.0", &self.raft_margin)
+                .on_input(Message::RaftMarginChanged),
+            text("Chamfer (μm):"),
+            text_input("1000.0", &self.raft_chamfer)
+                .on_input(Message::RaftChamferChanged),
+            checkbox("Crenelated Edge", self.raft_crenelation)
+                .on_toggle(Message::RaftCrenelationToggled),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let hollow_row = row![
+            checkbox("Hollow", self.hollow)
+                .on_toggle(Message::HollowToggled),
+        ];
+
+        let hollow_settings = column![
+            row![
+                text("Wall (μm):"),
+                text_input("2000.0", &self.wall_thickness)
+                    .on_input(Message::WallThicknessChanged),
+                checkbox("Infill", self.infill)
+                    .on_toggle(Message::InfillToggled),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center),
+        ]
+        .push_maybe(self.infill.then(|| {
+            row![
+                pick_list(InfillPattern::ALL, Some(self.infill_pattern), Message::InfillPatternSelected),
+                text("Density (%):"),
+                text_input("10.0", &self.infill_density)
+                    .on_input(Message::InfillDensityChanged),
+                text("Cell (μm):"),
+                text_input("5000.0", &self.infill_cell)
+                    .on_input(Message::InfillCellChanged),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        }))
+        .spacing(10);
+
+        let drain_row = row![
+            text("Drain Holes:").width(Length::Fixed(120.0)),
+            text_input("X,Y,Z,DX,DY,DZ,D,L (mm, μm); ...", &self.drain_holes)
+                .on_input(Message::DrainHolesChanged)
+                .width(Length::Fill),
+            checkbox("Auto", self.auto_drain_holes)
+                .on_toggle(Message::AutoDrainHolesToggled),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center)
+        .push_maybe(self.auto_drain_holes.then(|| {
+            row![
+                text("Diameter (μm):"),
+                text_input("3000.0", &self.drain_diameter)
+                    .on_input(Message::DrainDiameterChanged)
+                    .width(Length::Fixed(80.0)),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+        }));
+
+        let budget_row = row![
+            text("Memory Budget (MB):").width(Length::Fixed(120.0)),
+            text_input("Unlimited", &self.memory_budget)
+                .on_input(Message::MemoryBudgetChanged)
+                .width(Length::Fill),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let engine_row = row![
+            text("Engine:").width(Length::Fixed(120.0)),
+            pick_list(SliceEngine::ALL, Some(self.engine), Message::EngineSelected),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let svg_row = row![
+            text("SVG Contours:").width(Length::Fixed(120.0)),
+            pick_list(SvgOutput::ALL, Some(self.svg_output), Message::SvgOutputSelected),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let format_row = row![
+            text("Image Format:").width(Length::Fixed(120.0)),
+            pick_list(LayerFormat::ALL, Some(self.layer_format), Message::LayerFormatSelected),
+            text("PNG Compression:"),
+            pick_list(PngCompression::ALL, Some(self.png_compression), Message::PngCompressionSelected),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        let orientation_row = row![
+            text("Orientation:").width(Length::Fixed(120.0)),
+            checkbox("Mirror X", self.orientation.mirror_x)
+                .on_toggle(Message::MirrorXToggled),
+            checkbox("Mirror Y", self.orientation.mirror_y)
+                .on_toggle(Message::MirrorYToggled),
+            text("Rotation:"),
+            pick_list(Rotation::ALL, Some(self.orientation.rotation), Message::RotationSelected),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
         let checkboxes = column![
             checkbox("Zero Slice Position", self.zero_slice_position)
                 .on_toggle(Message::ZeroSliceToggled),
@@ -254,6 +986,33 @@ impl SlicerApp {
                 .on_toggle(Message::DeleteOutputDirToggled),
             checkbox("Open Output Directory When Done", self.open_output_dir)
                 .on_toggle(Message::OpenOutputDirToggled),
+            checkbox("Warn About Enclosed Cavities", self.cavity_check)
+                .on_toggle(Message::CavityCheckToggled),
+            row![
+                checkbox("Warn About Islands", self.island_check)
+                    .on_toggle(Message::IslandCheckToggled),
+            ]
+            .push_maybe(self.island_check.then(|| {
+                checkbox("Save Overlays", self.islands.overlays)
+                    .on_toggle(Message::IslandOverlaysToggled)
+            }))
+            .push_maybe(self.island_check.then(|| {
+                checkbox("Fail On Islands", self.islands.strict)
+                    .on_toggle(Message::StrictToggled)
+            }))
+            .spacing(10),
+            row![
+                checkbox("Export Area Profile", self.area_profile)
+                    .on_toggle(Message::AreaProfileToggled),
+            ]
+            .push_maybe(self.area_profile.then(|| text("Flag Jumps Over (mm²):")))
+            .push_maybe(self.area_profile.then(|| {
+                text_input("100.0", &self.area_jump)
+                    .on_input(Message::AreaJumpChanged)
+                    .width(100)
+            }))
+            .spacing(10)
+            .align_y(Alignment::Center),
         ]
         .spacing(8);
 
@@ -261,18 +1020,42 @@ impl SlicerApp {
             title_row,
             input_row,
             output_row,
+            file_name_row,
             pixel_row,
             layer_row,
-            checkboxes,
+            ranges_row,
+            adaptive_row,
         ]
+        .push_maybe(self.adaptive.then_some(adaptive_settings))
+        .push(supports_row)
+        .push_maybe(self.supports.then_some(supports_settings))
+        .push(raft_row)
+        .push_maybe(self.raft.then_some(raft_settings))
+        .push(hollow_row)
+        .push_maybe(self.hollow.then_some(hollow_settings))
+        .push(drain_row)
+        .push(resin_row)
+        .push(offset_row)
+        .push(elephant_foot_row)
+        .push_maybe(self.elephant_foot.then_some(elephant_foot_settings))
+        .push(budget_row)
+        .push(engine_row)
+        .push(svg_row)
+        .push(format_row)
+        .push(orientation_row)
+        .push(checkboxes)
         .spacing(15)
         .padding(20);
 
         if self.is_processing {
             content = content.push(progress_bar(0.0..=1.0, self.progress));
+
+            if let Some(phase_progress) = self.phase_progress {
+                content = content.push(progress_bar(0.0..=1.0, phase_progress).height(8));
+            }
             
             if let Some(ref time) = self.estimated_time {
-                content = content.push(text(format!("Estimated time remaining: {}", time)));
+                content = content.push(text(format!("Estimated time remaining in phase: {}", time)));
             }
         }
 
@@ -284,6 +1067,24 @@ impl SlicerApp {
 
         content = content.push(slice_button);
         content = content.push(text(&self.status_message).size(14));
+        if !self.orientations.is_empty() {
+            content = content.push(text("Orientations, best first:").size(14));
+        }
+        for orientation in &self.orientations {
+            content = content.push(text(orientation).size(14));
+        }
+        if let Some((chart, caption)) = &self.area_chart {
+            content = content.push(text("Cross-section area by height:").size(14));
+            content = content.push(iced::widget::image(chart.clone()));
+            content = content.push(text(caption).size(14));
+        }
+        for warning in &self.warnings {
+            content = content.push(
+                text(format!("Warning: {}", warning))
+                    .size(14)
+                    .style(text::danger),
+            );
+        }
 
         container(content)
             .width(Length::Fill)
@@ -295,3 +1096,44 @@ impl SlicerApp {
         Theme::default()
     }
 }
+
+/// Draws the cross-section area of each layer against its height, flagged
+/// jumps in red, with a caption giving the scales. Returns `None` for an
+/// empty profile.
+fn area_chart(profile: &[LayerArea]) -> Option<(iced::widget::image::Handle, String)> {
+    let (first, last) = (profile.first()?, profile.last()?);
+    let z_span = (last.z_mm - first.z_mm).max(f32::EPSILON);
+    let peak = profile.iter().fold(0.0f32, |peak, layer| peak.max(layer.area_mm2));
+    let column = |z: f32| (((z - first.z_mm) / z_span * (CHART_WIDTH - 1) as f32) as u32).min(CHART_WIDTH - 1);
+
+    // Largest area in each column, and whether it holds a jump. Each layer
+    // reaches across to the next one, so sparse layers draw as steps.
+    let mut columns = vec![(0.0f32, false); CHART_WIDTH as usize];
+    for (i, layer) in profile.iter().enumerate() {
+        let start = column(layer.z_mm);
+        let end = profile.get(i + 1).map_or(CHART_WIDTH, |next| column(next.z_mm).max(start + 1));
+        for (area, jump) in &mut columns[start as usize..end.min(CHART_WIDTH) as usize] {
+            *area = area.max(layer.area_mm2);
+            *jump |= layer.jump;
+        }
+    }
+
+    let mut pixels = Vec::with_capacity((CHART_WIDTH * CHART_HEIGHT * 4) as usize);
+    for y in 0..CHART_HEIGHT {
+        let level = (CHART_HEIGHT - y) as f32 / CHART_HEIGHT as f32 * peak;
+        for &(area, jump) in &columns {
+            let color = match (area > 0.0 && area >= level, jump) {
+                (true, true) => [220, 50, 50, 255],
+                (true, false) => [60, 120, 200, 255],
+                (false, _) => [235, 235, 235, 255],
+            };
+            pixels.extend_from_slice(&color);
+        }
+    }
+
+    let caption = format!(
+        "Z {:.2} to {:.2} mm across, 0 to {:.1} mm² up",
+        first.z_mm, last.z_mm, peak
+    );
+    Some((iced::widget::image::Handle::from_rgba(CHART_WIDTH, CHART_HEIGHT, pixels), caption))
+}
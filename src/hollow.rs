@@ -0,0 +1,222 @@
+//! Hollowing solid models down to a shell, with optional infill.
+//!
+//! A pixel is hollowed out when the ball of the wall thickness around it lies
+//! entirely inside the model. The 3D distance to the surface is exact up to
+//! the pixel grid: the traced spans give each pixel's distance to the
+//! surface straight up or down, and a 2D distance transform over those
+//! heights finds the nearest surface point in any direction.
+
+use crate::spans::{Span, SpanGrid};
+use crate::{morphology, pixel_inside, PlateGrid, SliceEngine, SliceError, SlicerConfig};
+use serde::Serialize;
+use std::f32::consts::TAU;
+use std::fmt;
+
+/// Shape of the lattice left inside a hollowed model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InfillPattern {
+    /// Square rods along X, Y and Z through the corners of cubic cells.
+    #[default]
+    Grid,
+    /// A gyroid sheet, which has no horizontal overhangs to support.
+    Gyroid,
+}
+
+impl InfillPattern {
+    pub const ALL: [InfillPattern; 2] = [InfillPattern::Grid, InfillPattern::Gyroid];
+}
+
+impl fmt::Display for InfillPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InfillPattern::Grid => write!(f, "Grid"),
+            InfillPattern::Gyroid => write!(f, "Gyroid"),
+        }
+    }
+}
+
+/// Lattice filling the hollow interior to stiffen thin walls.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Infill {
+    pub pattern: InfillPattern,
+    /// Share of the interior volume that is filled, in percent.
+    pub density_pct: f32,
+    /// Size of one repeat of the pattern, in micrometers.
+    pub cell_size_um: f32,
+}
+
+impl Default for Infill {
+    fn default() -> Self {
+        Self {
+            pattern: InfillPattern::Grid,
+            density_pct: 10.0,
+            cell_size_um: 5000.0,
+        }
+    }
+}
+
+/// Removal of material deeper inside the model than the wall thickness.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Hollowing {
+    /// Thickness of the shell left around the hollow, in micrometers.
+    pub wall_thickness_um: f32,
+    pub infill: Option<Infill>,
+}
+
+impl Default for Hollowing {
+    fn default() -> Self {
+        Self {
+            wall_thickness_um: 2000.0,
+            infill: None,
+        }
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    let Some(hollowing) = config.hollowing else {
+        return Ok(());
+    };
+    if config.engine != SliceEngine::Raycast {
+        return Err(SliceError::InvalidConfig("hollowing needs the raycast engine".to_string()));
+    }
+    if !hollowing.wall_thickness_um.is_finite() || hollowing.wall_thickness_um <= 0.0 {
+        return Err(SliceError::InvalidConfig("wall thickness must be positive".to_string()));
+    }
+    if let Some(infill) = hollowing.infill {
+        if !(infill.density_pct > 0.0 && infill.density_pct <= 100.0) {
+            return Err(SliceError::InvalidConfig(
+                "infill density must be above 0% and at most 100%".to_string(),
+            ));
+        }
+        if !infill.cell_size_um.is_finite() || infill.cell_size_um <= 0.0 {
+            return Err(SliceError::InvalidConfig("infill cell size must be positive".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Renders hollowed layers from traced spans.
+pub(crate) struct Hollower {
+    wall_mm: f32,
+    infill: Option<Lattice>,
+}
+
+impl Hollower {
+    pub(crate) fn new(config: &SlicerConfig) -> Option<Self> {
+        let hollowing = config.hollowing?;
+        Some(Self {
+            wall_mm: hollowing.wall_thickness_um / 1000.0,
+            infill: hollowing.infill.map(Lattice::new),
+        })
+    }
+
+    /// Rows that must be traced beyond a band so that distances within it
+    /// see every surface closer than the wall thickness.
+    pub(crate) fn margin_rows(&self, grid: &PlateGrid) -> u32 {
+        (self.wall_mm / grid.pixel_size_y_mm).ceil() as u32 + 1
+    }
+
+    /// Which pixels of `spans` are solid at height `z`, in row-major order
+    /// with row 0 at plate row `first_row`.
+    pub(crate) fn solid(&self, grid: &PlateGrid, spans: &SpanGrid, first_row: u32, z: f32) -> Vec<bool> {
+        let (width, height) = (spans.width(), spans.height());
+        let wall_sq = (self.wall_mm as f64).powi(2);
+        let depths = morphology::squared_distances(
+            width,
+            height,
+            (grid.pixel_size_x_mm, grid.pixel_size_y_mm),
+            |x, y| match vertical_depth(spans.pixel(x, y), z) {
+                Some(depth) if depth < self.wall_mm => (depth as f64).powi(2),
+                Some(_) => f64::INFINITY,
+                None => 0.0,
+            },
+        );
+
+        let mut solid = vec![false; depths.len()];
+        for y in 0..height {
+            let model_y = (first_row + y) as f32 + 0.5;
+            for x in 0..width {
+                let i = (y * width + x) as usize;
+                if !pixel_inside(spans.pixel(x, y), z) {
+                    continue;
+                }
+                solid[i] = depths[i] <= wall_sq
+                    || self.infill.as_ref().is_some_and(|lattice| {
+                        lattice.contains(
+                            (x as f32 + 0.5) * grid.pixel_size_x_mm,
+                            model_y * grid.pixel_size_y_mm,
+                            z - grid.min_bound.z,
+                        )
+                    });
+            }
+        }
+        solid
+    }
+}
+
+/// Distance from height `z` to the nearest span end, if `z` is inside a span.
+fn vertical_depth(spans: &[Span], z: f32) -> Option<f32> {
+    spans
+        .iter()
+        .find(|(enter, exit)| z >= *enter && z <= *exit)
+        .map(|(enter, exit)| (z - enter).min(exit - z))
+}
+
+/// An infill pattern as the points where a periodic field falls below a
+/// threshold chosen to give the requested density.
+struct Lattice {
+    pattern: InfillPattern,
+    cell_mm: f32,
+    threshold: f32,
+}
+
+impl Lattice {
+    /// Samples per cell edge when choosing the threshold.
+    const SAMPLES: usize = 32;
+
+    fn new(infill: Infill) -> Self {
+        let mut lattice = Self {
+            pattern: infill.pattern,
+            cell_mm: infill.cell_size_um / 1000.0,
+            threshold: 0.0,
+        };
+        let step = lattice.cell_mm / Self::SAMPLES as f32;
+        let mut values: Vec<f32> = Vec::with_capacity(Self::SAMPLES.pow(3));
+        for i in 0..Self::SAMPLES.pow(3) {
+            let [x, y, z] = [i % Self::SAMPLES, i / Self::SAMPLES % Self::SAMPLES, i / Self::SAMPLES.pow(2)]
+                .map(|n| (n as f32 + 0.5) * step);
+            values.push(lattice.field(x, y, z));
+        }
+        values.sort_by(f32::total_cmp);
+        let filled = (values.len() as f32 * infill.density_pct / 100.0) as usize;
+        lattice.threshold = values.get(filled).copied().unwrap_or(f32::INFINITY);
+        lattice
+    }
+
+    /// Whether a point, in millimeters from the model's minimum corner, is
+    /// part of the infill.
+    fn contains(&self, x: f32, y: f32, z: f32) -> bool {
+        self.field(x, y, z) < self.threshold
+    }
+
+    fn field(&self, x: f32, y: f32, z: f32) -> f32 {
+        match self.pattern {
+            InfillPattern::Grid => {
+                // A point lies on a rod when it is close to a cell edge
+                // along two of the three axes.
+                let edge = |v: f32| {
+                    let r = v.rem_euclid(self.cell_mm);
+                    r.min(self.cell_mm - r)
+                };
+                let mut d = [edge(x), edge(y), edge(z)];
+                d.sort_by(f32::total_cmp);
+                d[1]
+            }
+            InfillPattern::Gyroid => {
+                let [x, y, z] = [x, y, z].map(|v| v * TAU / self.cell_mm);
+                (x.sin() * y.cos() + y.sin() * z.cos() + z.sin() * x.cos()).abs()
+            }
+        }
+    }
+}
@@ -0,0 +1,453 @@
+//! Planning the stack of layers to slice.
+
+use crate::{SliceError, SlicerConfig, Triangle};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Settings for adaptive layer heights.
+///
+/// Each layer is made as thick as possible, up to `max_height_um`, while
+/// keeping the stair-step cusp on every surface it crosses below
+/// `max_cusp_um`. A surface whose normal makes angle θ with Z leaves a cusp of
+/// `thickness * |cos θ|`, so near-vertical walls get thick layers and
+/// near-horizontal surfaces get thin ones.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AdaptiveLayers {
+    pub min_height_um: f32,
+    pub max_height_um: f32,
+    pub max_cusp_um: f32,
+}
+
+impl Default for AdaptiveLayers {
+    fn default() -> Self {
+        Self {
+            min_height_um: 10.0,
+            max_height_um: 100.0,
+            max_cusp_um: 20.0,
+        }
+    }
+}
+
+/// A band of the model, measured up from its lowest point, sliced with its
+/// own layer height.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LayerRange {
+    pub start_mm: f32,
+    pub end_mm: f32,
+    pub layer_height_um: f32,
+}
+
+impl FromStr for LayerRange {
+    type Err = String;
+
+    /// Parses `START-END:HEIGHT`, e.g. `0-5:50` for 50 µm layers over the
+    /// bottom 5 mm.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid layer range '{}', expected START_MM-END_MM:HEIGHT_UM", s);
+        let (span, height) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let (start, end) = span.split_once('-').ok_or_else(invalid)?;
+        Ok(LayerRange {
+            start_mm: start.trim().parse().map_err(|_| invalid())?,
+            end_mm: end.trim().parse().map_err(|_| invalid())?,
+            layer_height_um: height.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+impl fmt::Display for LayerRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}:{}", self.start_mm, self.end_mm, self.layer_height_um)
+    }
+}
+
+/// Parses a comma-separated list of layer ranges.
+pub fn parse_layer_ranges(s: &str) -> Result<Vec<LayerRange>, String> {
+    s.split(',')
+        .filter(|part| !part.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// A layer to be written, numbered in print order.
+#[derive(Debug, Clone)]
+pub(crate) struct Layer {
+    pub(crate) index: u32,
+    /// Height of the layer's bottom plane, where it is sampled.
+    pub(crate) z: f32,
+    pub(crate) thickness_mm: f32,
+    /// Distance from the bottom of the model in micrometers, accumulated in
+    /// micrometers so zero-based file names stay exact.
+    pub(crate) offset_um: f32,
+    /// File name without extension, filled in by `naming::assign_names`.
+    pub(crate) name: String,
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    let invalid = |v: f32| v.is_nan() || v <= 0.0;
+    match config.adaptive_layers {
+        None if invalid(config.layer_height_um) => {
+            return Err(SliceError::InvalidConfig("layer height must be positive".to_string()));
+        }
+        Some(adaptive)
+            if invalid(adaptive.min_height_um)
+                || invalid(adaptive.max_cusp_um)
+                || adaptive.max_height_um.is_nan()
+                || adaptive.max_height_um < adaptive.min_height_um =>
+        {
+            return Err(SliceError::InvalidConfig(
+                "adaptive layers need 0 < min height <= max height and a positive cusp".to_string(),
+            ));
+        }
+        _ => {}
+    }
+
+    for range in &config.layer_ranges {
+        let empty = range.start_mm.is_nan() || range.end_mm.is_nan() || range.end_mm <= range.start_mm;
+        if invalid(range.layer_height_um) || empty {
+            return Err(SliceError::InvalidConfig(format!(
+                "layer range {} needs start < end and a positive layer height",
+                range
+            )));
+        }
+    }
+    let mut ranges = config.layer_ranges.clone();
+    ranges.sort_by(|a, b| a.start_mm.total_cmp(&b.start_mm));
+    if let Some(pair) = ranges.windows(2).find(|pair| pair[1].start_mm < pair[0].end_mm) {
+        return Err(SliceError::InvalidConfig(format!(
+            "layer ranges {} and {} overlap",
+            pair[0], pair[1]
+        )));
+    }
+    Ok(())
+}
+
+/// Lists the layers to write, skipping those below zero if requested.
+/// Layers are numbered from zero in print order after skipping.
+pub(crate) fn plan_layers(
+    config: &SlicerConfig,
+    triangles: &[Triangle],
+    start_z: f32,
+    end_z: f32,
+) -> Vec<Layer> {
+    let slopes = config.adaptive_layers.map(|adaptive| SlopeMap::new(adaptive, triangles));
+    let default_thickness_um = |z: f32| match &slopes {
+        Some(slopes) => slopes.thickness_um(z),
+        None => config.layer_height_um,
+    };
+
+    let layers = if !config.layer_ranges.is_empty() {
+        let mut ranges = config.layer_ranges.clone();
+        ranges.sort_by(|a, b| a.start_mm.total_cmp(&b.start_mm));
+        // Stop short of `limit_um` so the next zone starts exactly where
+        // asked, unless that would leave a sliver thinner than a micrometer.
+        let clamp = |thickness_um: f32, offset_um: f32, limit_um: f32| {
+            if limit_um - offset_um >= 1.0 {
+                thickness_um.min(limit_um - offset_um)
+            } else {
+                thickness_um
+            }
+        };
+        stack_layers(start_z, end_z, |offset_um, z| {
+            let offset_mm = offset_um / 1000.0;
+            if let Some(range) = ranges
+                .iter()
+                .find(|r| r.start_mm <= offset_mm && offset_mm < r.end_mm)
+            {
+                return clamp(range.layer_height_um, offset_um, range.end_mm * 1000.0);
+            }
+            let thickness_um = default_thickness_um(z);
+            match ranges.iter().find(|r| r.start_mm > offset_mm) {
+                Some(next) => clamp(thickness_um, offset_um, next.start_mm * 1000.0),
+                None => thickness_um,
+            }
+        })
+    } else if slopes.is_some() {
+        stack_layers(start_z, end_z, |_, z| default_thickness_um(z))
+    } else {
+        uniform_layers(config.layer_height_um, start_z, end_z)
+    };
+
+    let mut layers: Vec<Layer> = layers
+        .into_iter()
+        .filter(|layer| !(config.delete_below_zero && layer.z < 0.0))
+        .collect();
+    for (index, layer) in layers.iter_mut().enumerate() {
+        layer.index = index as u32;
+    }
+    layers
+}
+
+fn uniform_layers(layer_height_um: f32, start_z: f32, end_z: f32) -> Vec<Layer> {
+    let layer_height_mm = layer_height_um / 1000.0;
+
+    // Calculate number of layers
+    let num_layers = ((end_z - start_z) / layer_height_mm).ceil() as u32;
+
+    (0..num_layers)
+        .map(|index| Layer {
+            index,
+            z: start_z + index as f32 * layer_height_mm,
+            thickness_mm: layer_height_mm,
+            offset_um: index as f32 * layer_height_um,
+            name: String::new(),
+        })
+        .collect()
+}
+
+/// Stacks layers from `start_z` until `end_z` is reached, asking
+/// `thickness_um(offset_um, z)` for the thickness of each one.
+fn stack_layers(
+    start_z: f32,
+    end_z: f32,
+    mut thickness_um: impl FnMut(f32, f32) -> f32,
+) -> Vec<Layer> {
+    let mut layers = Vec::new();
+    let mut offset_um = 0.0f32;
+    loop {
+        let z = start_z + offset_um / 1000.0;
+        if z >= end_z {
+            break;
+        }
+        let thickness = thickness_um(offset_um, z);
+        layers.push(Layer {
+            index: layers.len() as u32,
+            z,
+            thickness_mm: thickness / 1000.0,
+            offset_um,
+            name: String::new(),
+        });
+        offset_um += thickness;
+    }
+    layers
+}
+
+/// Face slopes indexed by height, for choosing adaptive layer thickness.
+struct SlopeMap {
+    adaptive: AdaptiveLayers,
+    bottom_z: f32,
+    /// Height of each bucket, the thickest layer allowed, so a layer
+    /// overlaps at most two buckets.
+    bucket_mm: f32,
+    /// `(min_z, max_z, |normal z|)` of the faces that can limit a layer's
+    /// thickness, listed in every bucket their Z range overlaps.
+    buckets: Vec<Vec<(f32, f32, f32)>>,
+}
+
+impl SlopeMap {
+    fn new(adaptive: AdaptiveLayers, triangles: &[Triangle]) -> Self {
+        // Faces this close to vertical allow the thickest layer anyway.
+        let limiting_nz = adaptive.max_cusp_um / adaptive.max_height_um;
+        let faces: Vec<(f32, f32, f32)> = triangles
+            .iter()
+            .filter_map(|t| {
+                let normal = (t.v1 - t.v0).cross(t.v2 - t.v0).try_normalize()?;
+                let min_z = t.v0.z.min(t.v1.z).min(t.v2.z);
+                let max_z = t.v0.z.max(t.v1.z).max(t.v2.z);
+                (normal.z.abs() > limiting_nz).then_some((min_z, max_z, normal.z.abs()))
+            })
+            .collect();
+
+        let bottom_z = faces.iter().map(|face| face.0).fold(f32::MAX, f32::min);
+        let top_z = faces.iter().map(|face| face.1).fold(f32::MIN, f32::max);
+        let bucket_mm = adaptive.max_height_um / 1000.0;
+        let mut map = Self { adaptive, bottom_z, bucket_mm, buckets: Vec::new() };
+        if faces.is_empty() {
+            return map;
+        }
+        map.buckets = vec![Vec::new(); map.bucket(top_z) + 1];
+        for face in faces {
+            for bucket in map.bucket(face.0)..=map.bucket(face.1) {
+                map.buckets[bucket].push(face);
+            }
+        }
+        map
+    }
+
+    /// Bucket holding height `z`, which may be past the last one.
+    fn bucket(&self, z: f32) -> usize {
+        ((z - self.bottom_z) / self.bucket_mm).floor().max(0.0) as usize
+    }
+
+    /// Thickest layer starting at `z` that respects the cusp limit.
+    fn thickness_um(&self, z: f32) -> f32 {
+        let adaptive = &self.adaptive;
+
+        // Shrink the layer until every face it crosses is within the cusp
+        // limit; shrinking can only drop faces, so this settles quickly.
+        let mut thickness_um = adaptive.max_height_um;
+        loop {
+            let top = z + thickness_um / 1000.0;
+            let end = (self.bucket(top) + 1).min(self.buckets.len());
+            let steepest = self
+                .buckets
+                .get(self.bucket(z)..end)
+                .unwrap_or_default()
+                .iter()
+                .flatten()
+                .filter(|(min_z, max_z, _)| *min_z < top && *max_z > z)
+                .map(|(_, _, nz)| *nz)
+                .fold(0.0f32, f32::max);
+            let allowed = if steepest > 0.0 {
+                (adaptive.max_cusp_um / steepest).clamp(adaptive.min_height_um, adaptive.max_height_um)
+            } else {
+                adaptive.max_height_um
+            };
+            if allowed >= thickness_um {
+                return thickness_um;
+            }
+            thickness_um = allowed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+
+    /// A sphere of `stacks` bands around the origin, as slicing sees it.
+    fn sphere(radius: f32, stacks: usize) -> Vec<Triangle> {
+        let point = |i: usize, j: usize| {
+            let (theta, phi) = (std::f32::consts::PI * i as f32 / stacks as f32, std::f32::consts::PI * j as f32 / stacks as f32);
+            Vec3::new(theta.sin() * phi.cos(), theta.sin() * phi.sin(), theta.cos()) * radius
+        };
+        let triangle = |v0, v1, v2| Triangle { v0, v1, v2, node_index: 0, part: 0 };
+        let mut triangles = Vec::new();
+        for i in 0..stacks {
+            for j in 0..2 * stacks {
+                let (a, b, c, d) = (point(i, j), point(i + 1, j), point(i + 1, j + 1), point(i, j + 1));
+                if i != 0 {
+                    triangles.push(triangle(a, b, d));
+                }
+                if i != stacks - 1 {
+                    triangles.push(triangle(b, c, d));
+                }
+            }
+        }
+        triangles
+    }
+
+    fn adaptive_config(adaptive: AdaptiveLayers) -> SlicerConfig {
+        SlicerConfig { adaptive_layers: Some(adaptive), delete_below_zero: false, ..Default::default() }
+    }
+
+    #[test]
+    fn adaptive_layers_stay_within_limits() {
+        let adaptive = AdaptiveLayers::default();
+        let triangles = sphere(5.0, 32);
+        let layers = plan_layers(&adaptive_config(adaptive), &triangles, -5.0, 5.0);
+
+        let um = |mm: f32| mm * 1000.0;
+        assert!(layers.iter().any(|layer| um(layer.thickness_mm) > adaptive.min_height_um + 1.0));
+        assert!(layers.iter().any(|layer| um(layer.thickness_mm) < adaptive.max_height_um - 1.0));
+        for layer in &layers {
+            let thickness_um = um(layer.thickness_mm);
+            assert!(thickness_um >= adaptive.min_height_um - 1e-3, "layer {} too thin", layer.index);
+            assert!(thickness_um <= adaptive.max_height_um + 1e-3, "layer {} too thick", layer.index);
+            if thickness_um <= adaptive.min_height_um + 1e-3 {
+                continue;
+            }
+            let top = layer.z + layer.thickness_mm;
+            for t in &triangles {
+                let (min_z, max_z) = (t.v0.z.min(t.v1.z).min(t.v2.z), t.v0.z.max(t.v1.z).max(t.v2.z));
+                if min_z < top && max_z > layer.z {
+                    let nz = (t.v1 - t.v0).cross(t.v2 - t.v0).normalize().z.abs();
+                    assert!(thickness_um * nz <= adaptive.max_cusp_um + 1e-2, "layer {} cusp too large", layer.index);
+                }
+            }
+        }
+        assert!(layers.last().is_some_and(|last| last.z + last.thickness_mm >= 5.0));
+    }
+
+    #[test]
+    fn slope_buckets_match_a_scan_of_every_face() {
+        let adaptive = AdaptiveLayers { min_height_um: 5.0, max_height_um: 200.0, max_cusp_um: 15.0 };
+        let triangles = sphere(5.0, 24);
+        let slopes = SlopeMap::new(adaptive, &triangles);
+        let scan = |z: f32| {
+            let mut thickness_um = adaptive.max_height_um;
+            loop {
+                let top = z + thickness_um / 1000.0;
+                let steepest = triangles
+                    .iter()
+                    .filter(|t| t.v0.z.min(t.v1.z).min(t.v2.z) < top && t.v0.z.max(t.v1.z).max(t.v2.z) > z)
+                    .map(|t| (t.v1 - t.v0).cross(t.v2 - t.v0).normalize().z.abs())
+                    .fold(0.0f32, f32::max);
+                let allowed = match steepest {
+                    0.0 => adaptive.max_height_um,
+                    nz => (adaptive.max_cusp_um / nz).clamp(adaptive.min_height_um, adaptive.max_height_um),
+                };
+                if allowed >= thickness_um {
+                    return thickness_um;
+                }
+                thickness_um = allowed;
+            }
+        };
+        for step in 0..=1000 {
+            let z = -5.5 + step as f32 * 0.011;
+            assert_eq!(slopes.thickness_um(z), scan(z), "at z {}", z);
+        }
+    }
+
+    fn planned(layer_ranges: &str, layer_height_um: f32, height_mm: f32) -> Vec<(f32, f32)> {
+        let config = SlicerConfig {
+            layer_height_um,
+            layer_ranges: parse_layer_ranges(layer_ranges).unwrap(),
+            ..Default::default()
+        };
+        plan_layers(&config, &[], 0.0, height_mm)
+            .iter()
+            .map(|layer| (layer.offset_um, layer.thickness_mm * 1000.0))
+            .collect()
+    }
+
+    fn assert_planned(actual: Vec<(f32, f32)>, expected: &[(f32, f32)]) {
+        assert_eq!(actual.len(), expected.len(), "planned {:?}", actual);
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a.0 - e.0).abs() < 1e-2 && (a.1 - e.1).abs() < 1e-2, "planned {:?}", actual);
+        }
+    }
+
+    #[test]
+    fn parses_layer_ranges() {
+        let ranges = parse_layer_ranges(" 0-1.5:50, ,2-3 : 25 ,").unwrap();
+        assert_eq!(
+            ranges,
+            [
+                LayerRange { start_mm: 0.0, end_mm: 1.5, layer_height_um: 50.0 },
+                LayerRange { start_mm: 2.0, end_mm: 3.0, layer_height_um: 25.0 },
+            ]
+        );
+        assert_eq!(parse_layer_ranges("").unwrap(), []);
+        for bad in ["0-1", "0:50", "a-1:50", "0-1:x", "0-1:50,2"] {
+            assert!(parse_layer_ranges(bad).is_err(), "accepted {}", bad);
+        }
+        assert_eq!(ranges[0].to_string().parse::<LayerRange>(), Ok(ranges[0]));
+    }
+
+    #[test]
+    fn default_layers_stop_at_the_next_range() {
+        // 30 µm default layers reach 60 µm, then stop 40 µm short of 0.1 mm.
+        assert_planned(
+            planned("0.1-0.2:50", 30.0, 0.25),
+            &[(0.0, 30.0), (30.0, 30.0), (60.0, 30.0), (90.0, 10.0), (100.0, 50.0), (150.0, 50.0), (200.0, 30.0), (230.0, 30.0)],
+        );
+    }
+
+    #[test]
+    fn range_layers_stop_at_the_range_end() {
+        // A 50 µm range only 30 µm tall gets one 30 µm layer, and the
+        // default layers resume where it ends.
+        assert_planned(
+            planned("0.02-0.05:50", 20.0, 0.1),
+            &[(0.0, 20.0), (20.0, 30.0), (50.0, 20.0), (70.0, 20.0), (90.0, 20.0)],
+        );
+        // Back-to-back ranges each start exactly where asked, and the
+        // default layers after the last one too.
+        assert_planned(
+            planned("0-0.0625:40,0.0625-0.125:25", 100.0, 0.2),
+            &[(0.0, 40.0), (40.0, 22.5), (62.5, 25.0), (87.5, 25.0), (112.5, 12.5), (125.0, 100.0)],
+        );
+    }
+}
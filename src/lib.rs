@@ -4,20 +4,257 @@ use bvh::bvh::BVH;
 use bvh::ray::Ray;
 use glam::Vec3;
 use rayon::prelude::*;
+use serde::Serialize;
+use std::fmt;
 use std::fs::{self, File};
-use std::sync::mpsc::Sender;
+use std::io;
+use std::ops::Range;
 use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, Clone)]
+mod analysis;
+mod cavity;
+mod compensation;
+pub mod contour;
+mod drain;
+mod group4;
+mod hollow;
+mod layers;
+mod manifest;
+mod morphology;
+mod naming;
+mod orient;
+mod orientation;
+mod output;
+mod profile;
+mod progress;
+mod raft;
+mod resin;
+pub mod spans;
+mod supports;
+mod svg;
+mod tiled;
+
+pub use analysis::{EnclosedCavity, Island, IslandCheck};
+pub use compensation::{ElephantFoot, XyOffset};
+pub use contour::{FillRule, Polygon};
+pub use drain::{parse_drain_holes, AutoDrainHoles, DrainHole};
+pub use hollow::{Hollowing, Infill, InfillPattern};
+pub use layers::{parse_layer_ranges, AdaptiveLayers, LayerRange};
+pub use manifest::LayerInfo;
+pub use naming::DEFAULT_FILE_NAME_TEMPLATE;
+pub use orient::{find_orientations, AutoOrient, OrientWeights, OrientationCandidate};
+pub use orientation::{Orientation, Rotation};
+pub use output::{LayerFormat, PngCompression};
+pub use profile::{area_profile, AreaProfile, LayerArea};
+pub use progress::{NoProgress, ProgressEvent, ProgressSink};
+pub use raft::{Crenelation, Raft};
+pub use resin::{Exposure, ResinProfile, ResinStore};
+pub use supports::{SupportStyle, Supports};
+pub use svg::SvgOutput;
+use contour::Sectioner;
+use layers::Layer;
+use hollow::Hollower;
+use output::LayerWriter;
+use raft::RaftOutline;
+use spans::{Span, SpanGrid, SpanRow};
+
+#[derive(Debug, Clone, Serialize)]
 pub struct SlicerConfig {
     pub input_path: String,
     pub output_dir: String,
+    /// Pixel size along X, and along Y too unless `pixel_size_y_um` is set.
     pub pixel_size_um: f32,
+    /// Pixel size along Y, for panels whose pixels are not square.
+    pub pixel_size_y_um: Option<f32>,
     pub layer_height_um: f32,
     pub zero_slice_position: bool,
     pub delete_below_zero: bool,
     pub delete_output_dir: bool,
     pub open_output_dir: bool,
+    /// Slice in row bands to keep span storage under this many megabytes.
+    /// `None` traces the whole plate at once.
+    pub memory_budget_mb: Option<u32>,
+    pub engine: SliceEngine,
+    /// Fill rule used by the scanline engine and SVG export.
+    pub fill_rule: FillRule,
+    pub svg_output: SvgOutput,
+    /// Vary layer thickness with surface slope instead of using
+    /// `layer_height_um` for every layer.
+    pub adaptive_layers: Option<AdaptiveLayers>,
+    /// Explicit layer heights for bands of the model. Heights elsewhere come
+    /// from `layer_height_um` or `adaptive_layers`.
+    pub layer_ranges: Vec<LayerRange>,
+    /// Layer file name without extension, e.g. `{model}_{index:05}`. See
+    /// [`DEFAULT_FILE_NAME_TEMPLATE`] for the original naming.
+    pub file_name_template: String,
+    pub layer_format: LayerFormat,
+    pub png_compression: PngCompression,
+    /// Mirroring and rotation of the layer images to suit the printer.
+    pub orientation: Orientation,
+    /// Resin being printed. Its shrinkage is compensated by scaling the mesh,
+    /// and its properties go into the manifest.
+    pub resin: Option<ResinProfile>,
+    /// Turn the model to the best orientation found before anything else
+    /// is done to it.
+    pub auto_orient: Option<AutoOrient>,
+    /// Raise the model off the plate and hold up its overhangs and low
+    /// points with generated supports.
+    pub supports: Option<Supports>,
+    /// Lift the model onto a raft drawn into the bottom layers.
+    pub raft: Option<Raft>,
+    /// Hollow the model out to a shell, optionally with infill.
+    pub hollowing: Option<Hollowing>,
+    /// Holes drilled into the model so resin can drain from its cavities.
+    pub drain_holes: Vec<DrainHole>,
+    /// Also drill a hole under every enclosed cavity found while slicing.
+    pub auto_drain_holes: Option<AutoDrainHoles>,
+    /// Grow or shrink every layer's cross-section.
+    pub xy_offset: XyOffset,
+    /// Shrink the bottom layers to counter overexposure spreading them.
+    pub elephant_foot: Option<ElephantFoot>,
+    /// Look through the rendered layers for cavities closed off from the
+    /// vat and warn about them.
+    pub cavity_check: bool,
+    /// Look for lit regions with nothing under them in the layer below.
+    pub islands: Option<IslandCheck>,
+    /// Export each layer's cross-section area and flag sudden jumps in it.
+    pub area_profile: Option<AreaProfile>,
+}
+
+impl Default for SlicerConfig {
+    /// The command line's defaults, with no input file and every optional
+    /// step off.
+    fn default() -> Self {
+        Self {
+            input_path: String::new(),
+            output_dir: "slices".to_string(),
+            pixel_size_um: 33.3333,
+            pixel_size_y_um: None,
+            layer_height_um: 20.0,
+            zero_slice_position: false,
+            delete_below_zero: true,
+            delete_output_dir: true,
+            open_output_dir: false,
+            memory_budget_mb: None,
+            engine: SliceEngine::default(),
+            fill_rule: FillRule::EvenOdd,
+            svg_output: SvgOutput::Off,
+            adaptive_layers: None,
+            layer_ranges: Vec::new(),
+            file_name_template: DEFAULT_FILE_NAME_TEMPLATE.to_string(),
+            layer_format: LayerFormat::Png,
+            png_compression: PngCompression::Balanced,
+            orientation: Orientation::default(),
+            resin: None,
+            auto_orient: None,
+            supports: None,
+            raft: None,
+            hollowing: None,
+            drain_holes: Vec::new(),
+            auto_drain_holes: None,
+            xy_offset: XyOffset::default(),
+            elephant_foot: None,
+            cavity_check: false,
+            islands: None,
+            area_profile: None,
+        }
+    }
+}
+
+/// Algorithm used to decide which pixels of a layer are solid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SliceEngine {
+    /// Cast one vertical ray per pixel and test each layer against the spans
+    /// between entry and exit hits.
+    #[default]
+    Raycast,
+    /// Intersect each layer plane with the mesh to build closed contours, then
+    /// scanline-fill them.
+    Scanline,
+}
+
+impl SliceEngine {
+    pub const ALL: [SliceEngine; 2] = [SliceEngine::Raycast, SliceEngine::Scanline];
+}
+
+impl fmt::Display for SliceEngine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceEngine::Raycast => write!(f, "Raycast"),
+            SliceEngine::Scanline => write!(f, "Scanline"),
+        }
+    }
+}
+
+/// Summary of a completed slicing job.
+#[derive(Debug, Clone)]
+pub struct SliceReport {
+    pub output_dir: String,
+    pub triangles: usize,
+    pub width_px: u32,
+    pub height_px: u32,
+    pub layers: Vec<LayerInfo>,
+    /// Cavities found by the cavity check, from the bottom up.
+    pub cavities: Vec<EnclosedCavity>,
+    /// Islands found by the island check, from the bottom up.
+    pub islands: Vec<Island>,
+    /// Orientations ranked by auto-orient, best first. The model was
+    /// sliced in the first.
+    pub orientations: Vec<OrientationCandidate>,
+    /// Cross-section of every layer, if the area profile was exported.
+    pub area_profile: Vec<LayerArea>,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug)]
+pub enum SliceError {
+    Io { context: String, source: io::Error },
+    Stl(io::Error),
+    Image(image::ImageError),
+    EmptyMesh,
+    InvalidConfig(String),
+    /// Islands were found and the island check is strict.
+    Islands(usize),
+    /// The plate traced at once holds more spans than a span grid can.
+    TooManySpans { count: usize, limit: usize },
+}
+
+impl fmt::Display for SliceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SliceError::Io { context, source } => write!(f, "{}: {}", context, source),
+            SliceError::Stl(e) => write!(f, "Could not parse STL: {}", e),
+            SliceError::Image(e) => write!(f, "Could not save image: {}", e),
+            SliceError::EmptyMesh => write!(f, "Input mesh contains no triangles"),
+            SliceError::InvalidConfig(msg) => write!(f, "Invalid configuration: {}", msg),
+            SliceError::Islands(count) => write!(f, "Found {} islands in strict mode", count),
+            SliceError::TooManySpans { count, limit } => write!(
+                f,
+                "Tracing needs at least {} spans, more than the {} one trace can hold; use a coarser pixel size or set a memory budget (--memory-budget) to trace in bands",
+                count, limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SliceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SliceError::Io { source, .. } => Some(source),
+            SliceError::Stl(e) => Some(e),
+            SliceError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl SliceError {
+    fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> SliceError {
+        let context = context.into();
+        move |source| SliceError::Io { context, source }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -26,6 +263,9 @@ struct Triangle {
     v1: Vec3,
     v2: Vec3,
     node_index: usize,
+    /// Closed solid the triangle belongs to: 0 for the model, another
+    /// number for each support. Solids may overlap and are unioned.
+    part: u32,
 }
 
 impl Bounded for Triangle {
@@ -47,6 +287,11 @@ impl BHShape for Triangle {
 }
 
 impl Triangle {
+    /// Whether the triangle's winding puts its normal on the +Z side.
+    fn faces_up(&self) -> bool {
+        (self.v1 - self.v0).cross(self.v2 - self.v0).z > 0.0
+    }
+
     // Möller–Trumbore intersection algorithm
     fn intersect(&self, ray: &Ray) -> Option<f32> {
         let epsilon = 1e-6;
@@ -63,7 +308,7 @@ impl Triangle {
         let s = ray.origin - self.v0;
         let u = f * s.dot(h);
 
-        if u < 0.0 || u > 1.0 {
+        if !(0.0..=1.0).contains(&u) {
             return None;
         }
 
@@ -84,29 +329,330 @@ impl Triangle {
     }
 }
 
-pub fn slice(config: SlicerConfig) {
-    slice_with_progress(config, None);
+pub fn slice(config: SlicerConfig) -> Result<SliceReport, SliceError> {
+    slice_with_progress(config, &NoProgress)
+}
+
+/// Loads a model and traces its spans over the whole plate, as the raycast
+/// engine does before rendering. Used to measure span storage.
+pub fn trace_spans(input_path: &str, pixel_size_um: f32) -> Result<SpanGrid, SliceError> {
+    if pixel_size_um.is_nan() || pixel_size_um <= 0.0 {
+        return Err(SliceError::InvalidConfig("pixel size must be positive".to_string()));
+    }
+    let mut triangles = load_triangles(input_path)?;
+    let grid = PlateGrid::new(&triangles, (pixel_size_um / 1000.0, pixel_size_um / 1000.0));
+    let bvh = BVH::build(&mut triangles);
+    Scene { bvh: &bvh, triangles: &triangles, grid }.trace_rows(0..grid.height_px, &|| {})
+}
+
+/// Slices `config.input_path`, reporting each phase to `progress`.
+///
+/// The final event is always either `Finished` or `Failed`.
+pub fn slice_with_progress(
+    config: SlicerConfig,
+    progress: &dyn ProgressSink,
+) -> Result<SliceReport, SliceError> {
+    let result = run_slice(&config, progress);
+    match &result {
+        Ok(report) => progress.report(ProgressEvent::Finished(report.clone())),
+        Err(e) => progress.report(ProgressEvent::Failed(e.to_string())),
+    }
+    result
+}
+
+fn run_slice(config: &SlicerConfig, progress: &dyn ProgressSink) -> Result<SliceReport, SliceError> {
+    let start_time = Instant::now();
+
+    let (pixel_x_um, pixel_y_um) = (config.pixel_size_um, config.pixel_size_y_um.unwrap_or(config.pixel_size_um));
+    if pixel_x_um.is_nan() || pixel_x_um <= 0.0 || pixel_y_um.is_nan() || pixel_y_um <= 0.0 {
+        return Err(SliceError::InvalidConfig("pixel size must be positive".to_string()));
+    }
+    layers::validate(config)?;
+    analysis::validate(config)?;
+    naming::validate(config)?;
+    compensation::validate(config)?;
+    hollow::validate(config)?;
+    drain::validate(config)?;
+    supports::validate(config)?;
+    raft::validate(config)?;
+    profile::validate(config)?;
+    if let Some(auto_orient) = &config.auto_orient {
+        orient::validate(auto_orient)?;
+    }
+    if let Some(resin) = &config.resin {
+        resin::validate(resin)?;
+    }
+    if config.memory_budget_mb == Some(0) {
+        return Err(SliceError::InvalidConfig("memory budget must be positive".to_string()));
+    }
+
+    progress.report(ProgressEvent::Loading);
+    log::info!("Loading STL...");
+    let mut triangles = load_triangles(&config.input_path)?;
+    let mut drain_holes = config.drain_holes.clone();
+    let orientations = match &config.auto_orient {
+        Some(auto_orient) => {
+            let total = orient::direction_count(auto_orient);
+            let done = AtomicU32::new(0);
+            progress.report(ProgressEvent::Orienting { done: 0, total });
+            let candidates = orient::rank(&triangles, auto_orient, &|| {
+                let done = done.fetch_add(1, Ordering::Relaxed) + 1;
+                progress.report(ProgressEvent::Orienting { done, total });
+            });
+            for (rank, candidate) in candidates.iter().enumerate() {
+                log::info!("Orientation {}: {}", rank + 1, candidate);
+            }
+            let rotation = orient::rotation(&candidates[0]);
+            orient::rotate(&mut triangles, rotation);
+            drain::rotate_holes(&mut drain_holes, rotation);
+            candidates
+        }
+        None => Vec::new(),
+    };
+    if let Some(resin) = &config.resin {
+        let (origin, scale) = resin::compensate_shrinkage(&mut triangles, resin);
+        drain::scale_holes(&mut drain_holes, origin, scale);
+    }
+    if let Some(supports) = &config.supports {
+        let lift_mm = supports::generate(supports, &mut triangles);
+        drain::move_holes(&mut drain_holes, Vec3::new(0.0, 0.0, lift_mm));
+    }
+    let raft = config.raft.as_ref().map(|raft| {
+        let outline = RaftOutline::lift(raft, &mut triangles, config.layer_height_um / 1000.0);
+        drain::move_holes(&mut drain_holes, Vec3::new(0.0, 0.0, outline.lift_mm()));
+        outline
+    });
+
+    let mut grid = PlateGrid::new(&triangles, (pixel_x_um / 1000.0, pixel_y_um / 1000.0));
+    let model_bounds = (grid.min_bound, grid.max_bound);
+    if let Some(raft) = &raft {
+        grid.include(raft.bounds());
+    }
+    grid.pad(config.xy_offset.growth_mm());
+    log::debug!("Bounds: Min {:?}, Max {:?}", grid.min_bound, grid.max_bound);
+    log::info!("Image size: {} x {}", grid.width_px, grid.height_px);
+
+    let mut layers = layers::plan_layers(config, &triangles, grid.min_bound.z, grid.max_bound.z);
+    naming::assign_names(config, &mut layers)?;
+    log::info!("{} layers planned", layers.len());
+
+    // Delete output directory if requested
+    progress.report(ProgressEvent::Writing);
+    if config.delete_output_dir && std::path::Path::new(&config.output_dir).exists() {
+        fs::remove_dir_all(&config.output_dir)
+            .map_err(SliceError::io("Could not delete output directory"))?;
+    }
+    
+    fs::create_dir_all(&config.output_dir)
+        .map_err(SliceError::io("Could not create output directory"))?;
+
+    let raft = raft.map(|raft| raft.rasterize(&grid));
+    let mut output = LayerWriter::new(config, &grid, layers.len(), drain_holes, raft)?;
+    let written = match config.engine {
+        SliceEngine::Raycast => {
+            progress.report(ProgressEvent::BuildingBvh { triangles: triangles.len() });
+            log::info!("Building BVH...");
+            let bvh = BVH::build(&mut triangles);
+
+            let scene = Scene { bvh: &bvh, triangles: &triangles, grid };
+            match config.memory_budget_mb {
+                Some(budget_mb) => {
+                    tiled::slice_tiled(config, &scene, &layers, &output, budget_mb, progress)?
+                }
+                None => slice_whole_plate(config, &scene, &layers, &mut output, progress)?,
+            }
+        }
+        SliceEngine::Scanline => slice_scanline(config, &triangles, &grid, &layers, &output, progress)?,
+    };
+    let drain_holes = output.drain_holes().to_vec();
+    let findings = output.finish(&layers);
+    for cavity in &findings.cavities {
+        log::warn!("{}", cavity);
+    }
+    for island in &findings.islands {
+        log::warn!("{}", island);
+    }
+
+    if config.svg_output != SvgOutput::Off {
+        progress.report(ProgressEvent::Finishing);
+        log::info!("Writing SVG contours...");
+        svg::export(config, &triangles, &grid, &layers)?;
+    }
+
+    progress.report(ProgressEvent::Finishing);
+    manifest::write(config, model_bounds, &grid, &written, &drain_holes, orientations.first(), &findings)?;
+    let area_profile = match &config.area_profile {
+        Some(settings) => {
+            let profile = area_profile(&written, settings);
+            for layer in profile.iter().filter(|layer| layer.jump) {
+                log::warn!("{}", layer);
+            }
+            profile::write(config, &profile)?;
+            profile
+        }
+        None => Vec::new(),
+    };
+    if config.islands.is_some_and(|check| check.strict) && !findings.islands.is_empty() {
+        return Err(SliceError::Islands(findings.islands.len()));
+    }
+    
+    log::info!("Done! {} layers written", written.len());
+    
+    // Open output directory if requested
+    if config.open_output_dir {
+        let _ = opener::open(&config.output_dir);
+    }
+
+    Ok(SliceReport {
+        output_dir: config.output_dir.clone(),
+        triangles: triangles.len(),
+        width_px: grid.width_px,
+        height_px: grid.height_px,
+        layers: written,
+        cavities: findings.cavities,
+        islands: findings.islands,
+        orientations,
+        area_profile,
+        elapsed: start_time.elapsed(),
+    })
 }
 
-pub fn slice_with_progress(config: SlicerConfig, progress_tx: Option<Sender<(f32, String)>>) {
-    let pixel_size_mm = config.pixel_size_um / 1000.0;
-    let layer_height_mm = config.layer_height_um / 1000.0;
+/// Traces the whole plate up front, then renders every layer from memory.
+fn slice_whole_plate(
+    config: &SlicerConfig,
+    scene: &Scene,
+    layers: &[Layer],
+    output: &mut LayerWriter,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<LayerInfo>, SliceError> {
+    let grid = &scene.grid;
+
+    // Pre-calculate spans for each pixel
+    progress.report(ProgressEvent::Raytracing { rows_done: 0, rows_total: grid.height_px });
+    log::info!("Raytracing pixels...");
 
-    let send_progress = |progress: f32, message: &str| {
-        if let Some(ref tx) = progress_tx {
-            let _ = tx.send((progress, message.to_string()));
+    let completed_rows = AtomicU32::new(0);
+    let spans = scene.trace_rows(0..grid.height_px, &|| {
+        let done = completed_rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if done.is_multiple_of(16) || done == grid.height_px {
+            progress.report(ProgressEvent::Raytracing { rows_done: done, rows_total: grid.height_px });
         }
+    })?;
+    log::debug!(
+        "Stored {} spans in {:.1} MiB",
+        spans.span_count(),
+        spans.heap_bytes() as f64 / (1024.0 * 1024.0)
+    );
+
+    let hollower = Hollower::new(config);
+    let solid = |layer: &Layer| match &hollower {
+        Some(hollower) => hollower.solid(grid, &spans, 0, layer.z),
+        None => (0..grid.height_px)
+            .flat_map(|y| (0..grid.width_px).map(move |x| (x, y)))
+            .map(|(x, y)| pixel_inside(spans.pixel(x, y), layer.z))
+            .collect(),
     };
 
-    send_progress(0.0, "Loading STL...");
-    println!("Loading STL...");
-    let mut file = File::open(&config.input_path).expect("Could not open input file");
-    let mesh = stl_io::read_stl(&mut file).expect("Could not parse STL");
+    if let Some(auto) = config.auto_drain_holes {
+        log::info!("Finding cavities...");
+        let (holes, raft) = (output.drain_holes(), output.raft());
+        let holes = drain::place(auto, grid, &spans, layers, raft, |layer| {
+            let mut solid = solid(layer);
+            if let Some(raft) = raft {
+                raft.fill(layer, &mut solid, grid.width_px);
+            }
+            drain::punch(holes, grid, layer.z, |x, y| solid[(y * grid.width_px + x) as usize] = false);
+            solid
+        });
+        output.add_drain_holes(holes);
+    }
+    let output = &*output;
+
+    // Generate images
+    log::info!("Generating slices...");
+    let total = layers.len() as u32;
+    progress.report(ProgressEvent::Rendering { layer: 0, total });
+    
+    // Use atomic counter for thread-safe progress tracking
+    let completed_layers = AtomicU32::new(0);
+    
+    // Render in chunks of ordered layers so the layer checks hold few at a time.
+    let mut written = Vec::with_capacity(layers.len());
+    for chunk in layers.chunks(rayon::current_num_threads()) {
+        let infos: Vec<LayerInfo> = chunk.par_iter().map(|layer| {
+            // Create image
+            let mut img = image::GrayImage::new(grid.width_px, grid.height_px);
+            let solid = solid(layer);
+        
+            for y in 0..grid.height_px {
+                for x in 0..grid.width_px {
+                    if solid[(y * grid.width_px + x) as usize] {
+                        img.put_pixel(x, grid.height_px - 1 - y, image::Luma([255]));
+                    } else {
+                        img.put_pixel(x, grid.height_px - 1 - y, image::Luma([0]));
+                    }
+                }
+            }
+        
+            let info = output.write(layer, img)?;
+        
+            // Update progress after completing each layer
+            let completed = completed_layers.fetch_add(1, Ordering::Relaxed) + 1;
+            if completed.is_multiple_of(5) || completed == total {
+                progress.report(ProgressEvent::Rendering { layer: completed, total });
+            }
+            Ok(info)
+        }).collect::<Result<_, SliceError>>()?;
+        written.extend(infos);
+    }
+    Ok(written)
+}
+
+/// Cuts each layer plane through the mesh and fills the resulting contours.
+///
+/// Only one layer image per thread is held at a time, so no memory budget is
+/// needed.
+fn slice_scanline(
+    config: &SlicerConfig,
+    triangles: &[Triangle],
+    grid: &PlateGrid,
+    layers: &[Layer],
+    output: &LayerWriter,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<LayerInfo>, SliceError> {
+    log::info!("Generating slices from contours...");
+    let sectioner = Sectioner::new(triangles);
+    let total = layers.len() as u32;
+    progress.report(ProgressEvent::Rendering { layer: 0, total });
+    let completed_layers = AtomicU32::new(0);
+
+    // Render in chunks of ordered layers so the layer checks hold few at a time.
+    let mut written = Vec::with_capacity(layers.len());
+    for chunk in layers.chunks(rayon::current_num_threads()) {
+        let infos: Vec<LayerInfo> = chunk.par_iter().map(|layer| {
+            let polygons = sectioner.section(layer.z + SECTION_OFFSET_MM);
+            let img = contour::fill(&polygons, grid, config.fill_rule);
+            let info = output.write(layer, img)?;
+
+            let completed = completed_layers.fetch_add(1, Ordering::Relaxed) + 1;
+            if completed.is_multiple_of(5) || completed == total {
+                progress.report(ProgressEvent::Rendering { layer: completed, total });
+            }
+            Ok(info)
+        }).collect::<Result<_, SliceError>>()?;
+        written.extend(infos);
+    }
+    Ok(written)
+}
+
+fn load_triangles(path: &str) -> Result<Vec<Triangle>, SliceError> {
+    let mut file = File::open(path)
+        .map_err(SliceError::io(format!("Could not open input file {}", path)))?;
+    let mesh = stl_io::read_stl(&mut file).map_err(SliceError::Stl)?;
     
     let mut triangles = Vec::new();
     
-    send_progress(0.05, &format!("Converting {} triangles...", mesh.faces.len()));
-    println!("Converting {} triangles...", mesh.faces.len());
+    log::debug!("Converting {} triangles...", mesh.faces.len());
     for face in mesh.faces {
         let v0 = mesh.vertices[face.vertices[0]];
         let v1 = mesh.vertices[face.vertices[1]];
@@ -117,153 +663,262 @@ pub fn slice_with_progress(config: SlicerConfig, progress_tx: Option<Sender<(f32
             v1: Vec3::new(v1[0], v1[1], v1[2]),
             v2: Vec3::new(v2[0], v2[1], v2[2]),
             node_index: 0,
+            part: 0,
         });
     }
 
-    send_progress(0.1, "Building BVH...");
-    println!("Building BVH...");
-    let bvh = BVH::build(&mut triangles);
+    if triangles.is_empty() {
+        return Err(SliceError::EmptyMesh);
+    }
+    Ok(triangles)
+}
 
-    // Determine bounds
-    let mut min_bound = Vec3::splat(f32::MAX);
-    let mut max_bound = Vec3::splat(f32::MIN);
+/// The pixel grid covering the model's XY footprint.
+#[derive(Debug, Clone, Copy)]
+struct PlateGrid {
+    min_bound: Vec3,
+    max_bound: Vec3,
+    /// Pixel width along X.
+    pixel_size_x_mm: f32,
+    /// Pixel height along Y.
+    pixel_size_y_mm: f32,
+    width_px: u32,
+    height_px: u32,
+}
+
+impl PlateGrid {
+    fn new(triangles: &[Triangle], (pixel_size_x_mm, pixel_size_y_mm): (f32, f32)) -> Self {
+        // Determine bounds
+        let mut min_bound = Vec3::splat(f32::MAX);
+        let mut max_bound = Vec3::splat(f32::MIN);
 
-    for tri in &triangles {
-        let aabb = tri.aabb();
-        min_bound = min_bound.min(aabb.min);
-        max_bound = max_bound.max(aabb.max);
+        for tri in triangles {
+            let aabb = tri.aabb();
+            min_bound = min_bound.min(aabb.min);
+            max_bound = max_bound.max(aabb.max);
+        }
+
+        let mut grid = Self {
+            min_bound,
+            max_bound,
+            pixel_size_x_mm,
+            pixel_size_y_mm,
+            width_px: 0,
+            height_px: 0,
+        };
+        grid.include((min_bound, max_bound));
+        grid
     }
 
-    println!("Bounds: Min {:?}, Max {:?}", min_bound, max_bound);
+    /// Grows the plate to cover the box from `min` to `max`.
+    fn include(&mut self, (min, max): (Vec3, Vec3)) {
+        self.min_bound = self.min_bound.min(min);
+        self.max_bound = self.max_bound.max(max);
+        let width_mm = self.max_bound.x - self.min_bound.x;
+        let height_mm = self.max_bound.y - self.min_bound.y;
+        self.width_px = (width_mm / self.pixel_size_x_mm).ceil() as u32;
+        self.height_px = (height_mm / self.pixel_size_y_mm).ceil() as u32;
+    }
 
-    let width_mm = max_bound.x - min_bound.x;
-    let height_mm = max_bound.y - min_bound.y;
-    
-    let width_px = (width_mm / pixel_size_mm).ceil() as u32;
-    let height_px = (height_mm / pixel_size_mm).ceil() as u32;
-    
-    println!("Image size: {} x {}", width_px, height_px);
+    /// Widens the plate by at least `margin_mm` on every side, in whole
+    /// pixels so the model is sampled at the same points.
+    fn pad(&mut self, margin_mm: f32) {
+        if margin_mm <= 0.0 {
+            return;
+        }
+        let margin = Vec3::new(
+            (margin_mm / self.pixel_size_x_mm).ceil() * self.pixel_size_x_mm,
+            (margin_mm / self.pixel_size_y_mm).ceil() * self.pixel_size_y_mm,
+            0.0,
+        );
+        self.include((self.min_bound - margin, self.max_bound + margin));
+    }
 
-    // Pre-calculate spans for each pixel
-    send_progress(0.15, "Raytracing pixels...");
-    println!("Raytracing pixels...");
-    
-    let bvh = &bvh;
-    let triangles = &triangles;
-    
-    // We use a flattened vector for the grid
-    let spans: Vec<Vec<(f32, f32)>> = (0..height_px).into_par_iter().flat_map(|y| {
-        (0..width_px).into_par_iter().map(move |x| {
-            let px = min_bound.x + (x as f32 + 0.5) * pixel_size_mm;
-            let py = min_bound.y + (y as f32 + 0.5) * pixel_size_mm;
+    /// Volume of one pixel of a layer.
+    fn voxel_mm3(&self, layer: &Layer) -> f64 {
+        self.pixel_size_x_mm as f64 * self.pixel_size_y_mm as f64 * layer.thickness_mm as f64
+    }
+}
+
+/// Rows each thread traces per batch before the batch is packed into the
+/// span grid.
+const ROWS_PER_THREAD: usize = 4;
+
+/// The mesh and acceleration structure that rays are cast against.
+struct Scene<'a> {
+    bvh: &'a BVH,
+    triangles: &'a [Triangle],
+    grid: PlateGrid,
+}
+
+impl Scene<'_> {
+    /// Casts one vertical ray per pixel in `rows`, calling `on_row` as each
+    /// row completes. Row `y` of the result corresponds to plate row
+    /// `rows.start + y`.
+    fn trace_rows(
+        &self,
+        rows: Range<u32>,
+        on_row: &(dyn Fn() + Sync),
+    ) -> Result<SpanGrid, SliceError> {
+        let mut spans = SpanGrid::with_capacity(self.grid.width_px, rows.len() as u32);
+
+        // Rows are traced in parallel a batch at a time and appended in
+        // order, so only one batch is held outside the grid
+        let batch = (rayon::current_num_threads() * ROWS_PER_THREAD) as u32;
+        for start in rows.clone().step_by(batch as usize) {
+            let end = (start + batch).min(rows.end);
+            let traced: Vec<SpanRow> = (start..end)
+                .into_par_iter()
+                .map(|y| {
+                    let row = self.trace_row(y);
+                    on_row();
+                    row
+                })
+                .collect();
+
+            // Reserve for this batch and the rows left at the density seen so
+            // far, rather than letting the buffer double past what it needs
+            let batch_spans: usize = traced.iter().map(SpanRow::span_count).sum();
+            let per_row = (spans.span_count() + batch_spans) / (spans.height() as usize + traced.len());
+            spans.reserve_spans(batch_spans + per_row * (rows.end - end) as usize);
+            for row in &traced {
+                spans.push_row(row).map_err(|overflow| SliceError::TooManySpans {
+                    count: overflow.spans,
+                    limit: SpanGrid::MAX_SPANS,
+                })?;
+            }
+        }
+        spans.shrink_to_fit();
+        Ok(spans)
+    }
+
+    /// Casts one vertical ray per pixel of plate row `y`.
+    fn trace_row(&self, y: u32) -> SpanRow {
+        let grid = &self.grid;
+        let mut row = SpanRow::with_capacity(grid.width_px);
+        let mut hits: Vec<(f32, bool, u32)> = Vec::new();
+        let mut pixel_spans: Vec<Span> = Vec::new();
+
+        for x in 0..grid.width_px {
+            let px = grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm;
+            let py = grid.min_bound.y + (y as f32 + 0.5) * grid.pixel_size_y_mm;
             
             // Ray from below the model pointing up
-            let origin = Vec3::new(px, py, min_bound.z - 1.0);
+            let origin = Vec3::new(px, py, grid.min_bound.z - 1.0);
             let direction = Vec3::new(0.0, 0.0, 1.0);
             let ray = Ray::new(origin, direction);
             
-            let hit_shapes = bvh.traverse(&ray, &triangles);
+            let hit_shapes = self.bvh.traverse(&ray, self.triangles);
             
-            let mut hits: Vec<f32> = Vec::new();
+            hits.clear();
             for shape in hit_shapes {
                 if let Some(dist) = shape.intersect(&ray) {
                     // Convert distance to Z value
                     let z = origin.z + dist * direction.z;
-                    hits.push(z);
+                    hits.push((z, shape.faces_up(), shape.part));
                 }
             }
             
-            hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            hits.sort_by(|a, b| a.2.cmp(&b.2).then(a.0.partial_cmp(&b.0).unwrap()));
+            merge_edge_hits(&mut hits);
             
-            // Create spans from pairs
-            let mut pixel_spans = Vec::new();
-            for i in (0..hits.len()).step_by(2) {
-                if i + 1 < hits.len() {
-                    pixel_spans.push((hits[i], hits[i+1]));
+            // Create spans from pairs within each part, then merge the
+            // parts' spans where they overlap
+            pixel_spans.clear();
+            for part in hits.chunk_by(|a, b| a.2 == b.2) {
+                for i in (0..part.len()).step_by(2) {
+                    if i + 1 < part.len() {
+                        pixel_spans.push((part[i].0, part[i+1].0));
+                    }
                 }
             }
-            pixel_spans
-        })
-    }).collect();
+            if hits.last().is_some_and(|last| last.2 != hits[0].2) {
+                pixel_spans.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+                pixel_spans.dedup_by(|next, span| {
+                    let overlaps = next.0 < span.1;
+                    if overlaps {
+                        span.1 = span.1.max(next.1);
+                    }
+                    overlaps
+                });
+            }
+            row.push_pixel(&pixel_spans);
+        }
+        row
+    }
+}
 
-    // Generate images
-    send_progress(0.5, "Generating slices...");
-    println!("Generating slices...");
-    
-    // Delete output directory if requested
-    if config.delete_output_dir && std::path::Path::new(&config.output_dir).exists() {
-        fs::remove_dir_all(&config.output_dir).expect("Could not delete output directory");
+/// Counts a ray through an edge or vertex shared by several triangles of a
+/// part once. Each of those triangles reports the crossing, and the extra
+/// hits would pair up the wrong way and drop or invert the pixel's spans,
+/// as along the diagonals splitting a box's faces. Hits within 0.01 µm facing
+/// the same way are merged; facing opposite ways, the ray only grazes a ridge
+/// or valley of the surface, entering and leaving at once, so both stay.
+///
+/// `hits` are `(z, faces_up, part)`, sorted by part and then by Z.
+fn merge_edge_hits(hits: &mut Vec<(f32, bool, u32)>) {
+    hits.dedup_by(|b, a| b.2 == a.2 && b.0 - a.0 < 1e-5 && b.1 == a.1);
+}
+
+fn layer_file_name(layer: &Layer, extension: &str) -> String {
+    format!("{}.{}", layer.name, extension)
+}
+
+fn layer_path(config: &SlicerConfig, layer: &Layer, extension: &str) -> String {
+    format!("{}/{}", config.output_dir, layer_file_name(layer, extension))
+}
+
+/// Height above each layer's Z at which the scanline engine cuts, so that a
+/// face lying exactly on the layer plane counts as solid like the raycast
+/// engine's span tolerance does.
+pub(crate) const SECTION_OFFSET_MM: f32 = 1e-4;
+
+/// Whether a pixel with the given spans is solid at height `z`.
+fn pixel_inside(spans: &[Span], z: f32) -> bool {
+    // Add a small epsilon to handle floating point inaccuracies,
+    // especially for flat surfaces aligned with the slice height.
+    let epsilon = 1e-4;
+    spans
+        .iter()
+        .any(|(enter, exit)| z >= *enter - epsilon && z <= *exit + epsilon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The triangles of an axis-aligned box, facing out.
+    pub(crate) fn cuboid(min: Vec3, max: Vec3) -> Vec<Triangle> {
+        let corner = |i: usize| Vec3::select(glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        quads
+            .iter()
+            .flat_map(|q| [[q[0], q[1], q[2]], [q[0], q[2], q[3]]])
+            .map(|[a, b, c]| Triangle { v0: corner(a), v1: corner(b), v2: corner(c), node_index: 0, part: 0 })
+            .collect()
     }
-    
-    fs::create_dir_all(&config.output_dir).expect("Could not create output directory");
 
-    let start_z = min_bound.z;
-    let end_z = max_bound.z;
-    
-    // Calculate number of layers
-    let num_layers = ((end_z - start_z) / layer_height_mm).ceil() as u32;
-    
-    // Use atomic counter for thread-safe progress tracking
-    let completed_layers = AtomicU32::new(0);
-    let progress_tx_clone = progress_tx.clone();
-    
-    (0..num_layers).into_par_iter().for_each(|i| {
-        let z = start_z + i as f32 * layer_height_mm;
-        
-        if config.delete_below_zero && z < 0.0 {
-            return;
-        }
+    #[test]
+    fn merges_hits_on_edges_facing_the_same_way() {
+        // Up through the bottom of a box along a diagonal, then out of its top
+        // through a vertex shared by three triangles.
+        let mut hits = vec![(0.0, false, 0), (0.0, false, 0), (2.0, true, 0), (2.0, true, 0), (2.0, true, 0)];
+        merge_edge_hits(&mut hits);
+        assert_eq!(hits, [(0.0, false, 0), (2.0, true, 0)]);
+    }
 
-        // Create image
-        let mut img = image::GrayImage::new(width_px, height_px);
-        
-        for y in 0..height_px {
-            for x in 0..width_px {
-                let idx = (y * width_px + x) as usize;
-                let pixel_spans = &spans[idx];
-                
-                let mut inside = false;
-                // Add a small epsilon to handle floating point inaccuracies,
-                // especially for flat surfaces aligned with the slice height.
-                let epsilon = 1e-4; 
-                for (enter, exit) in pixel_spans {
-                    if z >= *enter - epsilon && z <= *exit + epsilon {
-                        inside = true;
-                        break;
-                    }
-                }
-                
-                if inside {
-                    img.put_pixel(x, height_px - 1 - y, image::Luma([255]));
-                } else {
-                    img.put_pixel(x, height_px - 1 - y, image::Luma([0]));
-                }
-            }
-        }
-        
-        let z_microns = if config.zero_slice_position {
-            (i as f32 * config.layer_height_um).round() as i32
-        } else {
-            (z * 1000.0).round() as i32
-        };
-        let filename = format!("{}/{}.png", config.output_dir, z_microns);
-        img.save(filename).expect("Could not save image");
-        
-        // Update progress after completing each layer
-        let completed = completed_layers.fetch_add(1, Ordering::Relaxed) + 1;
-        if completed % 5 == 0 || completed == num_layers {
-            let progress = 0.5 + (completed as f32 / num_layers as f32) * 0.5;
-            if let Some(ref tx) = progress_tx_clone {
-                let _ = tx.send((progress, format!("Processing layer {} of {}", completed, num_layers)));
-            }
-        }
-    });
-    
-    send_progress(1.0, "Done!");
-    println!("Done!");
-    
-    // Open output directory if requested
-    if config.open_output_dir {
-        let _ = opener::open(&config.output_dir);
+    #[test]
+    fn keeps_hits_grazing_a_ridge() {
+        // A ray touching the peak of a roof enters and leaves at once.
+        let mut hits = vec![(1.0, false, 0), (1.0, true, 0), (3.0, false, 0), (3.0, true, 0)];
+        merge_edge_hits(&mut hits);
+        assert_eq!(hits.len(), 4);
+    }
+
+    #[test]
+    fn keeps_hits_of_other_parts_and_heights() {
+        let mut hits = vec![(0.0, false, 0), (1.0, true, 0), (1.0, false, 1), (1.0 + 1e-4, false, 1)];
+        merge_edge_hits(&mut hits);
+        assert_eq!(hits.len(), 4);
     }
 }
@@ -1,7 +1,9 @@
-use rs_licer::{slice, SlicerConfig};
+use rs_licer::{AreaProfile, AutoOrient, parse_drain_holes, IslandCheck, parse_layer_ranges, slice_with_progress, AdaptiveLayers, AutoDrainHoles, ElephantFoot, FillRule, Hollowing, Crenelation, Raft, SupportStyle, Supports, Infill, InfillPattern, Orientation, ResinStore, Rotation, XyOffset, SliceEngine, SlicerConfig, SvgOutput, LayerFormat, PngCompression, DEFAULT_FILE_NAME_TEMPLATE};
 use std::env;
+use terminal::{TerminalProgress, Verbosity};
 
 mod gui_iced;
+mod terminal;
 
 fn print_help() {
     println!("rs-licer - STL slicer for resin 3D printing");
@@ -18,11 +20,65 @@ fn print_help() {
     println!("    --gui                      Launch GUI mode");
     println!("    -h, --help                 Print help information");
     println!("    -p, --pixel-size <UM>      Pixel size in micrometers (default: 33.3333)");
+    println!("    --pixel-size-y <UM>        Pixel size along Y for non-square pixels (default: same as X)");
     println!("    -l, --layer-height <UM>    Layer height in micrometers (default: 20.0)");
+    println!("    --layer-ranges <RANGES>    Layer heights for Z bands above the model bottom,");
+    println!("                               e.g. 0-5:50,5-20:20 (START_MM-END_MM:HEIGHT_UM)");
+    println!("    --adaptive                 Vary layer height with surface slope (default: false)");
+    println!("    --min-layer-height <UM>    Thinnest adaptive layer in micrometers (default: 10.0)");
+    println!("    --max-layer-height <UM>    Thickest adaptive layer in micrometers (default: 100.0)");
+    println!("    --max-cusp <UM>            Largest adaptive stair-step cusp in micrometers (default: 20.0)");
+    println!("    --xy-offset <UM>           Grow (positive) or shrink (negative) every layer (default: 0)");
+    println!("    --hole-offset <UM>         Offset of hole walls instead of --xy-offset; negative opens holes");
+    println!("    --elephant-foot <UM>       Shrink the bottom layers by this distance (default: off)");
+    println!("    --elephant-foot-layers <N> Bottom layers shrunk by the full distance (default: 5)");
+    println!("    --elephant-foot-fade <N>   Further layers over which the shrinking fades out (default: 0)");
+    println!("    --auto-orient              Turn the model to the best of the orientations tried");
+    println!("    --orient-samples <N>       Orientations tried by --auto-orient besides the six axes (default: 256)");
+    println!("    --supports <STYLE>         Raise the model on generated supports: pillar or tree (default: off)");
+    println!("    --support-angle <DEG>      Support downward faces closer than this to flat (default: 45)");
+    println!("    --support-spacing <UM>     Distance between support contacts (default: 2500)");
+    println!("    --support-tip <UM>         Diameter where supports touch the model (default: 400)");
+    println!("    --support-pillar <UM>      Diameter of support pillars (default: 1000)");
+    println!("    --support-base <UM>        Diameter of support feet on the plate (default: 3000)");
+    println!("    --support-elevation <UM>   Height the model is raised to (default: 5000)");
+    println!("    --raft <UM>                Lift the model onto a raft this thick (default: off)");
+    println!("    --raft-margin <UM>         How far the raft reaches past the model's footprint (default: 2000)");
+    println!("    --raft-chamfer <UM>        How far the raft's edge slopes in towards its top (default: 1000)");
+    println!("    --raft-lines <UM>          Break the raft's edge into lines and gaps this wide (default: off)");
+    println!("    --hollow <UM>              Hollow the model, leaving walls this thick (default: off)");
+    println!("    --infill <PATTERN>         Infill inside a hollowed model: grid or gyroid (default: none)");
+    println!("    --infill-density <PCT>     Share of the hollow filled by infill (default: 10)");
+    println!("    --infill-cell <UM>         Size of one infill cell in micrometers (default: 5000)");
+    println!("    --drain-hole <HOLE>        Drill a hole, as X,Y,Z,DX,DY,DZ,DIAMETER_UM,DEPTH_UM from the");
+    println!("                               mouth at X,Y,Z mm towards DX,DY,DZ; repeat or separate with ';'");
+    println!("    --auto-drain-holes <UM>    Drill a hole this wide under every enclosed cavity (default: off)");
+    println!("    --resin <NAME>             Resin profile to compensate shrinkage for and estimate usage with");
+    println!("    --resin-file <PATH>        Resin profile store (default: ~/.config/rs-licer/resins.json)");
+    println!("    --list-resins              List the available resin profiles and exit");
     println!("    --zero-slice-position      Position model at slice zero (default: false)");
     println!("    --keep-above-zero          Keep slices above zero (default: delete below zero)");
+    println!("    --file-name <TEMPLATE>     Layer file name without extension, using {{index:05}},");
+    println!("                               {{z_um}}, {{z_mm:.3}} and {{model}} (default: {{z_um}})");
     println!("    --keep-output-dir          Don't delete existing output directory (default: delete)");
     println!("    --open-output-dir          Open output directory when done (default: false)");
+    println!("    --cavity-check             Warn about cavities closed off from the vat (default: off)");
+    println!("    --island-check             Warn about regions with nothing under them (default: off)");
+    println!("    --island-overlays          Save an image marking the islands of each layer that has any");
+    println!("    --strict                   Fail if any island is found");
+    println!("    --area-profile             Export each layer's cross-section area as CSV and JSON");
+    println!("    --area-jump <MM2>          Flag layers whose area grows by more than this (default: 100)");
+    println!("    --engine <ENGINE>          Slicing engine: raycast or scanline (default: raycast)");
+    println!("    --fill-rule <RULE>         Scanline fill rule: even-odd or nonzero (default: even-odd)");
+    println!("    --svg <MODE>               Also export layer contours as SVG: per-layer or combined");
+    println!("    --format <FORMAT>          Layer image format: png, bilevel-png, tiff or tiff-stack (default: png)");
+    println!("    --compression <LEVEL>      PNG compression: fast, balanced or best (default: balanced)");
+    println!("    --mirror-x                 Mirror layer images left to right");
+    println!("    --mirror-y                 Mirror layer images top to bottom");
+    println!("    --rotate <DEGREES>         Rotate layer images clockwise by 0, 90, 180 or 270 (default: 0)");
+    println!("    --memory-budget <MB>       Slice in row bands to limit memory use (default: unlimited)");
+    println!("    -q, --quiet                Only print errors");
+    println!("    -v, --verbose              Print detailed diagnostics");
     println!();
     println!("EXAMPLES:");
     println!("    rs-licer model.stl output/");
@@ -46,6 +102,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
     
+    if args.iter().any(|arg| arg == "--list-resins") {
+        list_resins(&args);
+        return Ok(());
+    }
+    
     // Parse CLI arguments for headless mode
     if args.len() < 3 {
         eprintln!("Error: Missing required arguments");
@@ -57,11 +118,48 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut input_path = String::new();
     let mut output_dir = String::new();
     let mut pixel_size_um = 33.3333;
+    let mut pixel_size_y_um = None;
     let mut layer_height_um = 20.0;
     let mut zero_slice_position = false;
     let mut delete_below_zero = true;
     let mut delete_output_dir = true;
     let mut open_output_dir = false;
+    let mut cavity_check = false;
+    let mut island_check = false;
+    let mut islands = IslandCheck::default();
+    let mut area_profile = None;
+    let mut area_jump_mm2 = None;
+    let mut memory_budget_mb = None;
+    let mut engine = SliceEngine::Raycast;
+    let mut fill_rule = FillRule::EvenOdd;
+    let mut svg_output = SvgOutput::Off;
+    let mut layer_format = LayerFormat::Png;
+    let mut png_compression = PngCompression::Balanced;
+    let mut orientation = Orientation::default();
+    let mut adaptive = false;
+    let mut adaptive_layers = AdaptiveLayers::default();
+    let mut xy_offset_um = 0.0;
+    let mut hole_offset_um = None;
+    let mut elephant_foot_enabled = false;
+    let mut elephant_foot = ElephantFoot::default();
+    let mut hollowing: Option<Hollowing> = None;
+    let mut infill_enabled = false;
+    let mut infill = Infill::default();
+    let mut auto_orient: Option<AutoOrient> = None;
+    let mut orient_samples = None;
+    let mut supports_enabled = false;
+    let mut supports = Supports::default();
+    let mut raft: Option<Raft> = None;
+    let mut raft_margin_um = None;
+    let mut raft_chamfer_um = None;
+    let mut raft_lines_um = None;
+    let mut drain_holes = Vec::new();
+    let mut auto_drain_holes = None;
+    let mut resin_name = None;
+    let mut resin_file = None;
+    let mut layer_ranges = Vec::new();
+    let mut file_name_template = DEFAULT_FILE_NAME_TEMPLATE.to_string();
+    let mut verbosity = Verbosity::Normal;
     
     let mut i = 1;
     while i < args.len() {
@@ -77,6 +175,17 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     std::process::exit(1);
                 });
             }
+            "--pixel-size-y" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --pixel-size-y requires a value");
+                    std::process::exit(1);
+                }
+                pixel_size_y_um = Some(args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid pixel size value");
+                    std::process::exit(1);
+                }));
+            }
             "-l" | "--layer-height" => {
                 i += 1;
                 if i >= args.len() {
@@ -88,6 +197,368 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     std::process::exit(1);
                 });
             }
+            "--engine" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --engine requires a value");
+                    std::process::exit(1);
+                }
+                engine = match args[i].as_str() {
+                    "raycast" => SliceEngine::Raycast,
+                    "scanline" => SliceEngine::Scanline,
+                    _ => {
+                        eprintln!("Error: Invalid engine, expected raycast or scanline");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--fill-rule" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --fill-rule requires a value");
+                    std::process::exit(1);
+                }
+                fill_rule = match args[i].as_str() {
+                    "even-odd" => FillRule::EvenOdd,
+                    "nonzero" => FillRule::NonZero,
+                    _ => {
+                        eprintln!("Error: Invalid fill rule, expected even-odd or nonzero");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--svg" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --svg requires a value");
+                    std::process::exit(1);
+                }
+                svg_output = match args[i].as_str() {
+                    "per-layer" => SvgOutput::PerLayer,
+                    "combined" => SvgOutput::Combined,
+                    _ => {
+                        eprintln!("Error: Invalid SVG mode, expected per-layer or combined");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--format" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --format requires a value");
+                    std::process::exit(1);
+                }
+                layer_format = match args[i].as_str() {
+                    "png" => LayerFormat::Png,
+                    "bilevel-png" => LayerFormat::BilevelPng,
+                    "tiff" => LayerFormat::Tiff,
+                    "tiff-stack" => LayerFormat::TiffStack,
+                    _ => {
+                        eprintln!("Error: Invalid format, expected png, bilevel-png, tiff or tiff-stack");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--compression" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --compression requires a value");
+                    std::process::exit(1);
+                }
+                png_compression = match args[i].as_str() {
+                    "fast" => PngCompression::Fast,
+                    "balanced" => PngCompression::Balanced,
+                    "best" => PngCompression::Best,
+                    _ => {
+                        eprintln!("Error: Invalid compression, expected fast, balanced or best");
+                        std::process::exit(1);
+                    }
+                };
+            }
+            "--memory-budget" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --memory-budget requires a value");
+                    std::process::exit(1);
+                }
+                memory_budget_mb = Some(args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid memory budget value");
+                    std::process::exit(1);
+                }));
+            }
+            "--layer-ranges" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --layer-ranges requires a value");
+                    std::process::exit(1);
+                }
+                layer_ranges = parse_layer_ranges(&args[i]).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                });
+            }
+            "--file-name" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --file-name requires a value");
+                    std::process::exit(1);
+                }
+                file_name_template = args[i].clone();
+            }
+            "--adaptive" => {
+                adaptive = true;
+            }
+            "--min-layer-height" | "--max-layer-height" | "--max-cusp" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: f32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                match flag.as_str() {
+                    "--min-layer-height" => adaptive_layers.min_height_um = value,
+                    "--max-layer-height" => adaptive_layers.max_height_um = value,
+                    _ => adaptive_layers.max_cusp_um = value,
+                }
+                adaptive = true;
+            }
+            "--xy-offset" | "--hole-offset" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: f32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                if flag == "--xy-offset" {
+                    xy_offset_um = value;
+                } else {
+                    hole_offset_um = Some(value);
+                }
+            }
+            "--elephant-foot" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --elephant-foot requires a value");
+                    std::process::exit(1);
+                }
+                elephant_foot.erosion_um = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid elephant-foot distance");
+                    std::process::exit(1);
+                });
+                elephant_foot_enabled = true;
+            }
+            "--elephant-foot-layers" | "--elephant-foot-fade" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: u32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                if flag == "--elephant-foot-layers" {
+                    elephant_foot.layers = value;
+                } else {
+                    elephant_foot.fade_layers = value;
+                }
+                elephant_foot_enabled = true;
+            }
+            "--auto-orient" => {
+                auto_orient = Some(AutoOrient::default());
+            }
+            "--orient-samples" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --orient-samples requires a value");
+                    std::process::exit(1);
+                }
+                orient_samples = Some(args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid number of orientations");
+                    std::process::exit(1);
+                }));
+            }
+            "--supports" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --supports requires a value");
+                    std::process::exit(1);
+                }
+                supports.style = match args[i].as_str() {
+                    "pillar" => SupportStyle::Pillar,
+                    "tree" => SupportStyle::Tree,
+                    _ => {
+                        eprintln!("Error: Invalid support style, expected pillar or tree");
+                        std::process::exit(1);
+                    }
+                };
+                supports_enabled = true;
+            }
+            "--support-angle" | "--support-spacing" | "--support-tip" | "--support-pillar" | "--support-base"
+            | "--support-elevation" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: f32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                match flag.as_str() {
+                    "--support-angle" => supports.overhang_angle_deg = value,
+                    "--support-spacing" => supports.spacing_um = value,
+                    "--support-tip" => supports.tip_diameter_um = value,
+                    "--support-pillar" => supports.pillar_diameter_um = value,
+                    "--support-base" => supports.base_diameter_um = value,
+                    _ => supports.elevation_um = value,
+                }
+                supports_enabled = true;
+            }
+            "--raft" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --raft requires a value");
+                    std::process::exit(1);
+                }
+                let thickness_um = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid raft thickness");
+                    std::process::exit(1);
+                });
+                raft = Some(Raft { thickness_um, ..Raft::default() });
+            }
+            "--raft-margin" | "--raft-chamfer" | "--raft-lines" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: f32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                match flag.as_str() {
+                    "--raft-margin" => raft_margin_um = Some(value),
+                    "--raft-chamfer" => raft_chamfer_um = Some(value),
+                    _ => raft_lines_um = Some(value),
+                }
+            }
+            "--hollow" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --hollow requires a value");
+                    std::process::exit(1);
+                }
+                let wall_thickness_um = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid wall thickness");
+                    std::process::exit(1);
+                });
+                hollowing = Some(Hollowing { wall_thickness_um, infill: None });
+            }
+            "--infill" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --infill requires a value");
+                    std::process::exit(1);
+                }
+                infill.pattern = match args[i].as_str() {
+                    "grid" => InfillPattern::Grid,
+                    "gyroid" => InfillPattern::Gyroid,
+                    _ => {
+                        eprintln!("Error: Invalid infill pattern, expected grid or gyroid");
+                        std::process::exit(1);
+                    }
+                };
+                infill_enabled = true;
+            }
+            "--infill-density" | "--infill-cell" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                let value: f32 = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid {} value", flag);
+                    std::process::exit(1);
+                });
+                if flag == "--infill-density" {
+                    infill.density_pct = value;
+                } else {
+                    infill.cell_size_um = value;
+                }
+                infill_enabled = true;
+            }
+            "--drain-hole" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --drain-hole requires a value");
+                    std::process::exit(1);
+                }
+                drain_holes.extend(parse_drain_holes(&args[i]).unwrap_or_else(|e| {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(1);
+                }));
+            }
+            "--auto-drain-holes" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --auto-drain-holes requires a value");
+                    std::process::exit(1);
+                }
+                let diameter_um = args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid drain hole diameter");
+                    std::process::exit(1);
+                });
+                auto_drain_holes = Some(AutoDrainHoles { diameter_um });
+            }
+            "--resin" | "--resin-file" => {
+                let flag = args[i].clone();
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: {} requires a value", flag);
+                    std::process::exit(1);
+                }
+                if flag == "--resin" {
+                    resin_name = Some(args[i].clone());
+                } else {
+                    resin_file = Some(args[i].clone());
+                }
+            }
+            "--mirror-x" => {
+                orientation.mirror_x = true;
+            }
+            "--mirror-y" => {
+                orientation.mirror_y = true;
+            }
+            "--rotate" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --rotate requires a value");
+                    std::process::exit(1);
+                }
+                orientation.rotation = match args[i].as_str() {
+                    "0" => Rotation::None,
+                    "90" => Rotation::Cw90,
+                    "180" => Rotation::Cw180,
+                    "270" => Rotation::Cw270,
+                    _ => {
+                        eprintln!("Error: Invalid rotation, expected 0, 90, 180 or 270");
+                        std::process::exit(1);
+                    }
+                };
+            }
             "--zero-slice-position" => {
                 zero_slice_position = true;
             }
@@ -100,6 +571,38 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "--open-output-dir" => {
                 open_output_dir = true;
             }
+            "--cavity-check" => {
+                cavity_check = true;
+            }
+            "--island-check" => {
+                island_check = true;
+            }
+            "--island-overlays" => {
+                islands.overlays = true;
+            }
+            "--strict" => {
+                islands.strict = true;
+            }
+            "--area-profile" => {
+                area_profile = Some(AreaProfile::default());
+            }
+            "--area-jump" => {
+                i += 1;
+                if i >= args.len() {
+                    eprintln!("Error: --area-jump requires a value");
+                    std::process::exit(1);
+                }
+                area_jump_mm2 = Some(args[i].parse().unwrap_or_else(|_| {
+                    eprintln!("Error: Invalid area jump value");
+                    std::process::exit(1);
+                }));
+            }
+            "-q" | "--quiet" => {
+                verbosity = Verbosity::Quiet;
+            }
+            "-v" | "--verbose" => {
+                verbosity = Verbosity::Verbose;
+            }
             arg if !arg.starts_with('-') => {
                 if input_path.is_empty() {
                     input_path = arg.to_string();
@@ -127,18 +630,95 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         std::process::exit(1);
     }
     
+    if infill_enabled {
+        let Some(hollowing) = hollowing.as_mut() else {
+            eprintln!("Error: Infill needs --hollow");
+            std::process::exit(1);
+        };
+        hollowing.infill = Some(infill);
+    }
+    
+    if let Some(samples) = orient_samples {
+        let Some(auto_orient) = auto_orient.as_mut() else {
+            eprintln!("Error: --orient-samples needs --auto-orient");
+            std::process::exit(1);
+        };
+        auto_orient.samples = samples;
+    }
+
+    if let Some(raft) = raft.as_mut() {
+        raft.margin_um = raft_margin_um.unwrap_or(raft.margin_um);
+        raft.chamfer_um = raft_chamfer_um.unwrap_or(raft.chamfer_um);
+        raft.crenelation = raft_lines_um.map(|width_um| Crenelation { line_width_um: width_um, gap_um: width_um });
+    } else if raft_margin_um.is_some() || raft_chamfer_um.is_some() || raft_lines_um.is_some() {
+        eprintln!("Error: Raft settings need --raft");
+        std::process::exit(1);
+    }
+
+    if let Some(threshold) = area_jump_mm2 {
+        let Some(area_profile) = area_profile.as_mut() else {
+            eprintln!("Error: --area-jump needs --area-profile");
+            std::process::exit(1);
+        };
+        area_profile.jump_threshold_mm2 = threshold;
+    }
+
+    if !island_check && (islands.overlays || islands.strict) {
+        eprintln!("Error: --island-overlays and --strict need --island-check");
+        std::process::exit(1);
+    }
+
+    let resin = resin_name.map(|name| {
+        let store = load_resin_store(resin_file.as_deref());
+        store.get(&name).cloned().unwrap_or_else(|| {
+            eprintln!("Error: Unknown resin '{}', see --list-resins", name);
+            std::process::exit(1);
+        })
+    });
+    
     let config = SlicerConfig {
         input_path,
         output_dir,
         pixel_size_um,
+        pixel_size_y_um,
         layer_height_um,
         zero_slice_position,
         delete_below_zero,
         delete_output_dir,
         open_output_dir,
+        memory_budget_mb,
+        engine,
+        fill_rule,
+        svg_output,
+        adaptive_layers: adaptive.then_some(adaptive_layers),
+        layer_ranges,
+        file_name_template,
+        layer_format,
+        png_compression,
+        orientation,
+        xy_offset: XyOffset {
+            outer_um: xy_offset_um,
+            hole_um: hole_offset_um.unwrap_or(xy_offset_um),
+        },
+        resin,
+        auto_orient,
+        supports: supports_enabled.then_some(supports),
+        raft,
+        hollowing,
+        drain_holes,
+        auto_drain_holes,
+        elephant_foot: elephant_foot_enabled.then_some(elephant_foot),
+        cavity_check,
+        islands: island_check.then_some(islands),
+        area_profile,
     };
 
-    slice(config);
+    terminal::init_logging(verbosity);
+    let progress = TerminalProgress::new(verbosity);
+    if let Err(e) = slice_with_progress(config, &progress) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
     Ok(())
 }
 
@@ -147,12 +727,32 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn example_config() -> SlicerConfig {
     SlicerConfig {
         input_path: "example.stl".to_string(),
-        output_dir: "slices".to_string(),
-        pixel_size_um: 33.3333,
-        layer_height_um: 20.0,
-        zero_slice_position: false,
-        delete_below_zero: true,
-        delete_output_dir: true,
-        open_output_dir: false,
+        ..Default::default()
+    }
+}
+
+/// Loads the resin store given with --resin-file, or the default one.
+fn load_resin_store(path: Option<&str>) -> ResinStore {
+    match path {
+        Some(path) => ResinStore::load(std::path::Path::new(path)).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }),
+        None => ResinStore::load_default(),
+    }
+}
+
+fn list_resins(args: &[String]) {
+    let path = args
+        .iter()
+        .position(|arg| arg == "--resin-file")
+        .and_then(|i| args.get(i + 1));
+    let store = load_resin_store(path.map(String::as_str));
+    for resin in &store.profiles {
+        let [x, y, z] = resin.shrinkage_pct;
+        println!(
+            "{:<24} {:.2} g/cm3, {:.2}/l, shrinkage {}% x {}% x {}%",
+            resin.name, resin.density_g_cm3, resin.cost_per_liter, x, y, z
+        );
     }
 }
@@ -0,0 +1,177 @@
+//! The `manifest.json` written alongside the layer images.
+
+use crate::analysis::{EnclosedCavity, Findings, Island};
+use crate::drain::DrainHole;
+use crate::layers::Layer;
+use crate::resin::ResinUsage;
+use crate::{Orientation, OrientationCandidate, PlateGrid, SliceError, SlicerConfig};
+use glam::Vec3;
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+pub(crate) const FILE_NAME: &str = "manifest.json";
+
+/// What was written for one layer.
+#[derive(Debug, Clone, Serialize)]
+pub struct LayerInfo {
+    pub index: u32,
+    pub z_mm: f32,
+    pub thickness_mm: f32,
+    pub file: String,
+    pub lit_pixels: u64,
+    pub area_mm2: f32,
+    /// Length of the outlines of the lit regions, holes included, measured
+    /// along pixel edges, so slanted and curved outlines read up to 4/π
+    /// times their true length.
+    pub perimeter_mm: f32,
+    /// Bounding box of lit pixels as `[x_min, y_min, x_max, y_max]` in image
+    /// pixel coordinates before `orientation`, inclusive, or `None` if
+    /// nothing is lit.
+    pub bbox_px: Option<[u32; 4]>,
+}
+
+/// Axis-aligned bounds of the sliced model in millimeters, supports
+/// included. The plate the layers cover also takes in the raft and room for
+/// XY offsets; see [`Transform`].
+#[derive(Debug, Clone, Copy, Serialize)]
+struct ModelBounds {
+    min_mm: [f32; 3],
+    max_mm: [f32; 3],
+}
+
+/// How image pixels map back onto the model.
+///
+/// Pixel `(col, row)` samples the model at
+/// `x = origin_mm[0] + (col + 0.5) * pixel_size_mm[0]` and, because rows are
+/// flipped, `y = origin_mm[1] + (image_height_px - row - 0.5) * pixel_size_mm[1]`.
+/// The image is then mirrored and rotated as `orientation` says, so the
+/// written files have their width and height swapped for quarter turns.
+/// The `{z_um}` and `{z_mm}` file name placeholders are layer Z minus
+/// `file_z_origin_mm`.
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Transform {
+    origin_mm: [f32; 2],
+    pixel_size_mm: [f32; 2],
+    image_width_px: u32,
+    image_height_px: u32,
+    flip_y: bool,
+    orientation: Orientation,
+    file_z_origin_mm: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest<'a> {
+    slicer: &'static str,
+    version: &'static str,
+    config: &'a SlicerConfig,
+    bounds: ModelBounds,
+    transform: Transform,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resin_usage: Option<ResinUsage>,
+    /// Orientation chosen by auto-orient, which the model was turned to
+    /// before slicing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orientation: Option<&'a OrientationCandidate>,
+    /// Every hole punched, placed automatically or not, on the mesh as
+    /// sliced.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    drain_holes: &'a [DrainHole],
+    /// Cavities closed off from the vat, if the cavity check ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cavities: Option<&'a [EnclosedCavity]>,
+    /// Islands, if the island check ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    islands: Option<&'a [Island]>,
+    layers: &'a [LayerInfo],
+}
+
+/// Measures a rendered layer for the manifest.
+pub(crate) fn layer_info(
+    grid: &PlateGrid,
+    layer: &Layer,
+    file: String,
+    img: &image::GrayImage,
+) -> LayerInfo {
+    let mut lit_pixels = 0u64;
+    // Pixel edges between a lit pixel and an unlit one or the border,
+    // running along Y and along X.
+    let (mut edges_y, mut edges_x) = (0u64, 0u64);
+    let (width, height) = img.dimensions();
+    let unlit = |x: Option<u32>, y: Option<u32>| match (x, y) {
+        (Some(x), Some(y)) if x < width && y < height => img.get_pixel(x, y).0[0] == 0,
+        _ => true,
+    };
+    let mut bbox: Option<[u32; 4]> = None;
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel.0[0] == 0 {
+            continue;
+        }
+        lit_pixels += 1;
+        edges_y += unlit(x.checked_sub(1), Some(y)) as u64 + unlit(Some(x + 1), Some(y)) as u64;
+        edges_x += unlit(Some(x), y.checked_sub(1)) as u64 + unlit(Some(x), Some(y + 1)) as u64;
+        bbox = Some(match bbox {
+            Some([x0, y0, x1, y1]) => [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+            None => [x, y, x, y],
+        });
+    }
+
+    LayerInfo {
+        index: layer.index,
+        z_mm: layer.z,
+        thickness_mm: layer.thickness_mm,
+        file,
+        lit_pixels,
+        area_mm2: lit_pixels as f32 * grid.pixel_size_x_mm * grid.pixel_size_y_mm,
+        perimeter_mm: edges_y as f32 * grid.pixel_size_y_mm + edges_x as f32 * grid.pixel_size_x_mm,
+        bbox_px: bbox,
+    }
+}
+
+/// Writes the manifest describing the job and every layer in print order,
+/// given the corners of the box around the model.
+pub(crate) fn write(
+    config: &SlicerConfig,
+    (min, max): (Vec3, Vec3),
+    grid: &PlateGrid,
+    layers: &[LayerInfo],
+    drain_holes: &[DrainHole],
+    orientation: Option<&OrientationCandidate>,
+    findings: &Findings,
+) -> Result<(), SliceError> {
+    let manifest = Manifest {
+        slicer: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        config,
+        bounds: ModelBounds { min_mm: min.to_array(), max_mm: max.to_array() },
+        transform: Transform {
+            origin_mm: [grid.min_bound.x, grid.min_bound.y],
+            pixel_size_mm: [grid.pixel_size_x_mm, grid.pixel_size_y_mm],
+            image_width_px: grid.width_px,
+            image_height_px: grid.height_px,
+            flip_y: true,
+            orientation: config.orientation,
+            file_z_origin_mm: if config.zero_slice_position { grid.min_bound.z } else { 0.0 },
+        },
+        resin_usage: config.resin.as_ref().map(|resin| ResinUsage::estimate(resin, layers)),
+        orientation,
+        drain_holes,
+        cavities: config.cavity_check.then_some(&findings.cavities[..]),
+        islands: config.islands.map(|_| &findings.islands[..]),
+        layers,
+    };
+    if let Some(usage) = &manifest.resin_usage {
+        log::info!(
+            "Estimated resin: {:.1} ml, {:.1} g, cost {:.2}",
+            usage.volume_ml,
+            usage.mass_g,
+            usage.cost
+        );
+    }
+
+    let path = Path::new(&config.output_dir).join(FILE_NAME);
+    let file = File::create(path).map_err(SliceError::io("Could not write manifest"))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), &manifest)
+        .map_err(|e| SliceError::io("Could not write manifest")(e.into()))
+}
@@ -0,0 +1,246 @@
+//! Distance-based erosion and dilation of binary layer images.
+//!
+//! Distances are exact Euclidean distances between pixel centers in
+//! millimeters, found with the separable transform of Felzenszwalb and
+//! Huttenlocher: a sweep down each column, then a lower envelope of
+//! parabolas along each row. Cost is linear in the pixel count whatever the
+//! radius.
+
+use image::{GrayImage, Luma};
+
+/// Clears lit pixels within `radius_mm` of an unlit pixel. Everything
+/// beyond the image edge counts as unlit.
+pub(crate) fn erode(img: &mut GrayImage, radius_mm: f32, pitch_mm: (f32, f32)) {
+    let field = DistanceField::new(img.width(), img.height(), true, |x, y| img.get_pixel(x, y).0[0] == 0);
+    field.within(radius_mm, pitch_mm, |x, y| img.put_pixel(x, y, Luma([0])));
+}
+
+/// Lights unlit pixels within `radius_mm` of a lit pixel.
+pub(crate) fn dilate(img: &mut GrayImage, radius_mm: f32, pitch_mm: (f32, f32)) {
+    let field = DistanceField::new(img.width(), img.height(), false, |x, y| img.get_pixel(x, y).0[0] != 0);
+    field.within(radius_mm, pitch_mm, |x, y| img.put_pixel(x, y, Luma([255])));
+}
+
+/// What each pixel of a layer belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Region {
+    Solid,
+    /// Unlit and connected to the image edge.
+    Outside,
+    /// Unlit and enclosed by solid pixels.
+    Hole,
+}
+
+/// Classifies every pixel, flooding the outside in from the image edge
+/// through 4-connected unlit pixels.
+pub(crate) fn regions(img: &GrayImage) -> Vec<Region> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let mut regions: Vec<Region> = img
+        .pixels()
+        .map(|pixel| if pixel.0[0] != 0 { Region::Solid } else { Region::Hole })
+        .collect();
+    if regions.is_empty() {
+        return regions;
+    }
+
+    let mut stack: Vec<usize> = Vec::new();
+    let edges = (0..width)
+        .flat_map(|x| [x, (height - 1) * width + x])
+        .chain((0..height).flat_map(|y| [y * width, y * width + width - 1]));
+    for start in edges {
+        if regions[start] == Region::Hole {
+            regions[start] = Region::Outside;
+            stack.push(start);
+        }
+    }
+    while let Some(i) = stack.pop() {
+        let (x, y) = (i % width, i / width);
+        let neighbors = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+        for j in neighbors.into_iter().flatten() {
+            if regions[j] == Region::Hole {
+                regions[j] = Region::Outside;
+                stack.push(j);
+            }
+        }
+    }
+    regions
+}
+
+/// Distances from every pixel to the nearest of a set of source pixels.
+pub(crate) struct DistanceField {
+    width: usize,
+    height: usize,
+    edge_is_source: bool,
+    /// Rows to the nearest source pixel in the same column, or u16::MAX.
+    rows_to_source: Vec<u16>,
+}
+
+impl DistanceField {
+    /// Builds the field for pixels where `is_source(x, y)` holds, plus
+    /// everything beyond the image edge if `edge_is_source` is set.
+    pub(crate) fn new(
+        width: u32,
+        height: u32,
+        edge_is_source: bool,
+        is_source: impl Fn(u32, u32) -> bool,
+    ) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let edge = if edge_is_source { 0 } else { u16::MAX };
+        let mut rows_to_source = vec![u16::MAX; width * height];
+        let mut run = vec![edge; width];
+        for y in 0..height {
+            for x in 0..width {
+                run[x] = if is_source(x as u32, y as u32) { 0 } else { run[x].saturating_add(1) };
+                rows_to_source[y * width + x] = run[x];
+            }
+        }
+        run.fill(edge);
+        for y in (0..height).rev() {
+            for x in 0..width {
+                run[x] = if is_source(x as u32, y as u32) { 0 } else { run[x].saturating_add(1) };
+                let cell = &mut rows_to_source[y * width + x];
+                *cell = (*cell).min(run[x]);
+            }
+        }
+        Self { width, height, edge_is_source, rows_to_source }
+    }
+
+    /// Calls `reach(x, y)` for every pixel within `radius_mm` of a source,
+    /// sources included. Nothing is reached for a radius that is not
+    /// positive.
+    pub(crate) fn within(&self, radius_mm: f32, pitch_mm: (f32, f32), mut reach: impl FnMut(u32, u32)) {
+        if radius_mm.is_nan() || radius_mm <= 0.0 {
+            return;
+        }
+        let (pitch_x, pitch_y) = (pitch_mm.0 as f64, pitch_mm.1 as f64);
+        // Slack so a neighbor exactly one radius away is reached despite rounding.
+        let radius_sq = (radius_mm as f64).powi(2) * (1.0 + 1e-6);
+        let mut envelope = Envelope::with_capacity(self.width + 2);
+        for y in 0..self.height {
+            envelope.clear();
+            if self.edge_is_source {
+                envelope.push(-pitch_x, 0.0);
+            }
+            for x in 0..self.width {
+                let rows = self.rows_to_source[y * self.width + x];
+                if rows != u16::MAX {
+                    envelope.push(x as f64 * pitch_x, (rows as f64 * pitch_y).powi(2));
+                }
+            }
+            if self.edge_is_source {
+                envelope.push(self.width as f64 * pitch_x, 0.0);
+            }
+            envelope.evaluate(self.width, pitch_x, |x, distance_sq| {
+                if distance_sq <= radius_sq {
+                    reach(x as u32, y as u32);
+                }
+            });
+        }
+    }
+}
+
+/// Squared distances in millimeters from every pixel center to the nearest
+/// point of a set described per pixel by `height_sq(x, y)`: the squared
+/// distance from that pixel's center to the set along a third axis, or
+/// infinity if it is too far to matter. Everything beyond the image edge
+/// belongs to the set. Returned in row-major order.
+pub(crate) fn squared_distances(
+    width: u32,
+    height: u32,
+    pitch_mm: (f32, f32),
+    height_sq: impl Fn(u32, u32) -> f64,
+) -> Vec<f64> {
+    let (width, height) = (width as usize, height as usize);
+    let (pitch_x, pitch_y) = (pitch_mm.0 as f64, pitch_mm.1 as f64);
+    let mut distances = vec![f64::INFINITY; width * height];
+    let mut envelope = Envelope::with_capacity(width.max(height) + 2);
+    for x in 0..width {
+        envelope.clear();
+        envelope.push(-pitch_y, 0.0);
+        for y in 0..height {
+            let d = height_sq(x as u32, y as u32);
+            if d.is_finite() {
+                envelope.push(y as f64 * pitch_y, d);
+            }
+        }
+        envelope.push(height as f64 * pitch_y, 0.0);
+        envelope.evaluate(height, pitch_y, |y, d| distances[y * width + x] = d);
+    }
+    for row in distances.chunks_mut(width.max(1)) {
+        envelope.clear();
+        envelope.push(-pitch_x, 0.0);
+        for (x, d) in row.iter().enumerate() {
+            if d.is_finite() {
+                envelope.push(x as f64 * pitch_x, *d);
+            }
+        }
+        envelope.push(width as f64 * pitch_x, 0.0);
+        envelope.evaluate(width, pitch_x, |x, d| row[x] = d);
+    }
+    distances
+}
+
+/// Lower envelope of parabolas `(p - position)^2 + height`, added in order
+/// of increasing position.
+struct Envelope {
+    /// `(position, height)` of each parabola on the envelope.
+    parabolas: Vec<(f64, f64)>,
+    /// Where each envelope parabola takes over from the previous one.
+    starts: Vec<f64>,
+}
+
+impl Envelope {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            parabolas: Vec::with_capacity(capacity),
+            starts: Vec::with_capacity(capacity),
+        }
+    }
+
+    fn clear(&mut self) {
+        self.parabolas.clear();
+        self.starts.clear();
+    }
+
+    fn push(&mut self, position: f64, height: f64) {
+        loop {
+            let Some(&(last, last_height)) = self.parabolas.last() else {
+                self.parabolas.push((position, height));
+                self.starts.push(f64::NEG_INFINITY);
+                return;
+            };
+            let crossing = ((height + position * position) - (last_height + last * last))
+                / (2.0 * (position - last));
+            if crossing <= *self.starts.last().unwrap() {
+                self.parabolas.pop();
+                self.starts.pop();
+            } else {
+                self.parabolas.push((position, height));
+                self.starts.push(crossing);
+                return;
+            }
+        }
+    }
+
+    /// Calls `visit(x, distance_sq)` for pixel centers `x * pitch` with
+    /// `x` in `0..count`. Nothing is visited if the envelope is empty.
+    fn evaluate(&self, count: usize, pitch: f64, mut visit: impl FnMut(usize, f64)) {
+        if self.parabolas.is_empty() {
+            return;
+        }
+        let mut k = 0;
+        for x in 0..count {
+            let p = x as f64 * pitch;
+            while k + 1 < self.parabolas.len() && self.starts[k + 1] < p {
+                k += 1;
+            }
+            let (position, height) = self.parabolas[k];
+            visit(x, (p - position).powi(2) + height);
+        }
+    }
+}
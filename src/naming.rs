@@ -0,0 +1,269 @@
+//! Layer file names built from a user template.
+
+use crate::layers::Layer;
+use crate::{layer_file_name, manifest, output, profile, svg, SliceError, SlicerConfig, SvgOutput};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Template reproducing the original `<z_microns>.png` names.
+pub const DEFAULT_FILE_NAME_TEMPLATE: &str = "{z_um}";
+
+/// Files written next to the layers. No layer file may take one of their
+/// names, even in a job that does not write that file, so a layer is never
+/// mistaken for one.
+const RESERVED_FILE_NAMES: [&str; 5] = [
+    manifest::FILE_NAME,
+    output::STACK_FILE_NAME,
+    svg::COMBINED_FILE_NAME,
+    profile::CSV_FILE_NAME,
+    profile::JSON_FILE_NAME,
+];
+
+/// One piece of a parsed template.
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    /// Layer number in print order, zero-padded to `width` digits.
+    Index { width: usize },
+    /// Layer Z in whole micrometers, zero-padded to `width` digits.
+    ZMicrons { width: usize },
+    /// Layer Z in millimeters with `precision` decimals.
+    ZMillimeters { precision: usize },
+    /// Input file name without its extension.
+    Model,
+}
+
+/// A parsed file name template such as `{model}_{index:05}`.
+///
+/// Placeholders are `{index}`, `{z_um}`, `{z_mm}` and `{model}`. `index` and
+/// `z_um` take a width (`{index:05}`), `z_mm` takes a precision
+/// (`{z_mm:.3}`, three decimals by default). `{{` and `}}` are literal braces.
+/// The extension is appended by the writer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FileNameTemplate {
+    parts: Vec<Part>,
+}
+
+impl FileNameTemplate {
+    pub(crate) fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => placeholder.push(c),
+                            None => return Err(format!("unclosed '{{' in '{}'", template)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+                    parts.push(parse_placeholder(&placeholder)?);
+                }
+                '}' => return Err(format!("unmatched '}}' in '{}'", template)),
+                '/' | '\\' => {
+                    return Err(format!("'{}' must not contain path separators", template));
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        if parts.is_empty() {
+            return Err("file name template is empty".to_string());
+        }
+        Ok(Self { parts })
+    }
+
+    /// Renders the name of `layer`, without extension.
+    ///
+    /// Z values are measured from the bottom of the model when
+    /// `zero_based` is set, and from the build plate otherwise.
+    fn render(&self, layer: &Layer, zero_based: bool, model: &str) -> String {
+        let z_um = if zero_based { layer.offset_um } else { layer.z * 1000.0 };
+        let mut name = String::new();
+        for part in &self.parts {
+            let _ = match part {
+                Part::Literal(text) => write!(name, "{}", text),
+                Part::Index { width } => write!(name, "{:0width$}", layer.index, width = width),
+                Part::ZMicrons { width } => {
+                    write!(name, "{:0width$}", z_um.round() as i32, width = width)
+                }
+                Part::ZMillimeters { precision } => {
+                    // Round in micrometers first so `-0.000` never appears.
+                    let z_mm = z_um.round() / 1000.0 + 0.0;
+                    write!(name, "{:.precision$}", z_mm, precision = precision)
+                }
+                Part::Model => write!(name, "{}", model),
+            };
+        }
+        name
+    }
+}
+
+fn parse_placeholder(placeholder: &str) -> Result<Part, String> {
+    let (key, spec) = match placeholder.split_once(':') {
+        Some((key, spec)) => (key.trim(), Some(spec.trim())),
+        None => (placeholder.trim(), None),
+    };
+    let width = |spec: Option<&str>| -> Result<usize, String> {
+        match spec {
+            None => Ok(0),
+            Some(spec) => spec
+                .parse()
+                .map_err(|_| format!("invalid width '{}' for {{{}}}", spec, key)),
+        }
+    };
+    match key {
+        "index" => Ok(Part::Index { width: width(spec)? }),
+        "z_um" => Ok(Part::ZMicrons { width: width(spec)? }),
+        "z_mm" => {
+            let precision = match spec {
+                None => 3,
+                Some(spec) => spec
+                    .strip_prefix('.')
+                    .and_then(|digits| digits.parse().ok())
+                    .ok_or_else(|| format!("invalid precision '{}' for {{z_mm}}, expected e.g. .3", spec))?,
+            };
+            Ok(Part::ZMillimeters { precision })
+        }
+        "model" if spec.is_none() => Ok(Part::Model),
+        "model" => Err("{model} takes no format".to_string()),
+        _ => Err(format!(
+            "unknown placeholder {{{}}}, expected index, z_um, z_mm or model",
+            key
+        )),
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    configured_template(config).map(|_| ())
+}
+
+fn configured_template(config: &SlicerConfig) -> Result<FileNameTemplate, SliceError> {
+    FileNameTemplate::parse(&config.file_name_template)
+        .map_err(|e| SliceError::InvalidConfig(format!("file name template: {}", e)))
+}
+
+/// Names every planned layer, failing if two layers would share a file or
+/// a layer would take the name of another output file.
+pub(crate) fn assign_names(config: &SlicerConfig, layers: &mut [Layer]) -> Result<(), SliceError> {
+    let template = configured_template(config)?;
+    let svg_extension = (config.svg_output == SvgOutput::PerLayer).then_some("svg");
+    let extensions: Vec<&str> = config.layer_format.extension().into_iter().chain(svg_extension).collect();
+    let model = Path::new(&config.input_path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut seen: HashMap<String, u32> = HashMap::with_capacity(layers.len());
+    for layer in layers.iter_mut() {
+        layer.name = template.render(layer, config.zero_slice_position, &model);
+        if let Some(first) = seen.insert(layer.name.clone(), layer.index) {
+            return Err(SliceError::InvalidConfig(format!(
+                "layers {} and {} would both be written as '{}'; use a template with {{index}} \
+                 or more {{z_mm}} decimals",
+                first, layer.index, layer.name
+            )));
+        }
+        let reserved = extensions
+            .iter()
+            .map(|extension| layer_file_name(layer, extension))
+            .find(|file| RESERVED_FILE_NAMES.contains(&file.as_str()));
+        if let Some(file) = reserved {
+            return Err(SliceError::InvalidConfig(format!(
+                "layer {} would be written as '{}', which is reserved for another output file",
+                layer.index, file
+            )));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LayerFormat;
+
+    fn layer(index: u32, z: f32) -> Layer {
+        Layer { index, z, thickness_mm: 0.05, offset_um: z * 1000.0, name: String::new() }
+    }
+
+    fn render(template: &str, layer: &Layer) -> String {
+        FileNameTemplate::parse(template).unwrap().render(layer, false, "part")
+    }
+
+    fn assign(template: &str, layer_format: LayerFormat, layers: &mut [Layer]) -> Result<(), String> {
+        let config = SlicerConfig {
+            input_path: "models/part.stl".to_string(),
+            file_name_template: template.to_string(),
+            layer_format,
+            ..Default::default()
+        };
+        assign_names(&config, layers).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let layer = layer(7, 1.26);
+        assert_eq!(render("{z_um}", &layer), "1260");
+        assert_eq!(render("{model}_{index:05}", &layer), "part_00007");
+        assert_eq!(render("{z_um:6}-{z_mm}-{z_mm:.1}", &layer), "001260-1.260-1.3");
+        assert_eq!(render("{ index }", &layer), "7");
+    }
+
+    #[test]
+    fn braces_escape_by_doubling() {
+        assert_eq!(render("{{{index}}}", &layer(3, 0.0)), "{3}");
+        assert_eq!(render("a}}b{{c", &layer(3, 0.0)), "a}b{c");
+    }
+
+    #[test]
+    fn rejects_bad_templates() {
+        for (template, error) in [
+            ("{layer}", "unknown placeholder {layer}"),
+            ("{index:x}", "invalid width 'x'"),
+            ("{z_mm:3}", "invalid precision '3'"),
+            ("{model:5}", "{model} takes no format"),
+            ("{index", "unclosed '{'"),
+            ("index}", "unmatched '}'"),
+            ("out/{index}", "path separators"),
+            ("", "empty"),
+        ] {
+            let result = FileNameTemplate::parse(template);
+            assert!(result.as_ref().is_err_and(|e| e.contains(error)), "{:?} gave {:?}", template, result);
+        }
+    }
+
+    #[test]
+    fn rejects_layers_sharing_a_name() {
+        let mut layers = [layer(0, 0.0), layer(1, 0.0004), layer(2, 0.05)];
+        let error = assign("{z_um}", LayerFormat::Png, &mut layers).unwrap_err();
+        assert!(error.contains("layers 0 and 1 would both be written as '0'"), "{}", error);
+        assert!(assign("{z_um}_{index}", LayerFormat::Png, &mut layers).is_ok());
+        assert_eq!(layers.map(|layer| layer.name), ["0_0", "0_1", "50_2"]);
+    }
+
+    #[test]
+    fn rejects_reserved_file_names() {
+        let error = assign("layers", LayerFormat::Tiff, &mut [layer(0, 0.0)]).unwrap_err();
+        assert!(error.contains("'layers.tif', which is reserved"), "{}", error);
+        // The same name is free as a PNG.
+        assert!(assign("layers", LayerFormat::Png, &mut [layer(0, 0.0)]).is_ok());
+    }
+}
@@ -0,0 +1,320 @@
+//! Choosing which way up to print the model.
+//!
+//! Directions spread evenly over the sphere are tried as the model's up
+//! direction. Each is scored on a coarse voxel model of the rotated mesh,
+//! which is enough to compare directions but not to slice from.
+
+use crate::analysis::unsupported_regions;
+use crate::cavity::{CavitySweep, EmptyRegions};
+use crate::supports::Columns;
+use crate::{load_triangles, SliceError, Triangle};
+use glam::{Quat, Vec3};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::f32::consts::PI;
+use std::fmt;
+
+/// Voxels along the longest side of the model when scoring a direction.
+const RESOLUTION: f32 = 64.0;
+
+/// Candidates closer than this to a better one are left out, in degrees.
+const MIN_SEPARATION_DEG: f32 = 15.0;
+
+/// Directions always tried: the model's own up, which wins ties, and the
+/// rest of its axes, which the flat faces of designed parts tend to be
+/// square to. A flat face only rests on the plate when exactly level.
+const AXES: [Vec3; 6] = [Vec3::Z, Vec3::NEG_Z, Vec3::X, Vec3::NEG_X, Vec3::Y, Vec3::NEG_Y];
+
+/// Search for the orientation to print the model in.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AutoOrient {
+    /// Number of up directions tried, besides the model's own axes.
+    pub samples: u32,
+    /// Number of best orientations reported.
+    pub candidates: usize,
+    pub weights: OrientWeights,
+}
+
+impl Default for AutoOrient {
+    fn default() -> Self {
+        Self { samples: 256, candidates: 5, weights: OrientWeights::default() }
+    }
+}
+
+/// How much each measure counts towards an orientation's score. Measures
+/// are scaled by their worst value over all directions tried, so equal
+/// weights count them equally.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OrientWeights {
+    pub overhang: f32,
+    pub cross_section: f32,
+    pub islands: f32,
+    pub height: f32,
+    pub suction: f32,
+}
+
+impl Default for OrientWeights {
+    fn default() -> Self {
+        Self { overhang: 1.0, cross_section: 1.0, islands: 1.0, height: 1.0, suction: 1.0 }
+    }
+}
+
+/// An orientation and how it scored. Lower scores are better.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct OrientationCandidate {
+    /// Direction in the model's own coordinates that points up from the
+    /// plate.
+    pub up: [f32; 3],
+    pub score: f32,
+    /// Area of downward faces steeper than 45° that would need supports,
+    /// leaving out faces resting on the plate.
+    pub overhang_area_mm2: f32,
+    /// Largest cross-section, which sets the peel force.
+    pub max_cross_section_mm2: f32,
+    pub islands: u32,
+    pub height_mm: f32,
+    /// Volume of cavities closed off from the vat.
+    pub suction_volume_mm3: f32,
+}
+
+impl fmt::Display for OrientationCandidate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Up ({:.2}, {:.2}, {:.2}): score {:.3}, overhangs {:.1} mm², largest section {:.1} mm², \
+             {} islands, height {:.1} mm, suction {:.1} mm³",
+            self.up[0],
+            self.up[1],
+            self.up[2],
+            self.score,
+            self.overhang_area_mm2,
+            self.max_cross_section_mm2,
+            self.islands,
+            self.height_mm,
+            self.suction_volume_mm3
+        )
+    }
+}
+
+/// Loads a model and ranks orientations to print it in, best first.
+pub fn find_orientations(input_path: &str, settings: &AutoOrient) -> Result<Vec<OrientationCandidate>, SliceError> {
+    validate(settings)?;
+    Ok(rank(&load_triangles(input_path)?, settings, &|| {}))
+}
+
+pub(crate) fn validate(settings: &AutoOrient) -> Result<(), SliceError> {
+    if settings.candidates == 0 {
+        return Err(SliceError::InvalidConfig("auto-orient needs at least one candidate".to_string()));
+    }
+    let w = settings.weights;
+    let weights = [w.overhang, w.cross_section, w.islands, w.height, w.suction];
+    if !weights.iter().all(|weight| weight.is_finite() && *weight >= 0.0) {
+        return Err(SliceError::InvalidConfig("auto-orient weights must not be negative".to_string()));
+    }
+    Ok(())
+}
+
+/// Number of directions `rank` tries.
+pub(crate) fn direction_count(settings: &AutoOrient) -> u32 {
+    settings.samples + AXES.len() as u32
+}
+
+/// Scores the model's axes and `settings.samples` other directions,
+/// calling `on_done` after each, and returns the best few that differ.
+pub(crate) fn rank(
+    triangles: &[Triangle],
+    settings: &AutoOrient,
+    on_done: &(dyn Fn() + Sync),
+) -> Vec<OrientationCandidate> {
+    let directions: Vec<Vec3> = AXES.into_iter().chain(fibonacci_sphere(settings.samples)).collect();
+    // Each worker turns the mesh into one buffer for every direction it
+    // scores, rather than copying it anew each time.
+    let mut candidates: Vec<OrientationCandidate> = directions
+        .par_iter()
+        .map_init(Vec::new, |rotated, &up| {
+            let candidate = measure(triangles, up, rotated);
+            on_done();
+            candidate
+        })
+        .collect();
+
+    let worst = candidates.iter().fold([0.0f32; 5], |worst, c| {
+        let measures = measures(c);
+        std::array::from_fn(|i| worst[i].max(measures[i]))
+    });
+    let w = settings.weights;
+    let weights = [w.overhang, w.cross_section, w.islands, w.height, w.suction];
+    for candidate in &mut candidates {
+        let measures = measures(candidate);
+        candidate.score = (0..5)
+            .filter(|&i| worst[i] > 0.0)
+            .map(|i| weights[i] * measures[i] / worst[i])
+            .sum();
+    }
+    // Stable, so the model's own direction wins ties.
+    candidates.sort_by(|a, b| a.score.total_cmp(&b.score));
+
+    let min_cos = MIN_SEPARATION_DEG.to_radians().cos();
+    let mut best: Vec<OrientationCandidate> = Vec::new();
+    for candidate in candidates {
+        let up = Vec3::from_array(candidate.up);
+        if best.iter().all(|kept| Vec3::from_array(kept.up).dot(up) < min_cos) {
+            best.push(candidate);
+            if best.len() == settings.candidates {
+                break;
+            }
+        }
+    }
+    best
+}
+
+/// The rotation that turns `up` to +Z.
+pub(crate) fn rotation(candidate: &OrientationCandidate) -> Quat {
+    Quat::from_rotation_arc(Vec3::from_array(candidate.up).normalize(), Vec3::Z)
+}
+
+pub(crate) fn rotate(triangles: &mut [Triangle], rotation: Quat) {
+    for tri in triangles {
+        for v in [&mut tri.v0, &mut tri.v1, &mut tri.v2] {
+            *v = rotation * *v;
+        }
+    }
+}
+
+fn measures(candidate: &OrientationCandidate) -> [f32; 5] {
+    [
+        candidate.overhang_area_mm2,
+        candidate.max_cross_section_mm2,
+        candidate.islands as f32,
+        candidate.height_mm,
+        candidate.suction_volume_mm3,
+    ]
+}
+
+/// `count` directions spread evenly over the sphere.
+fn fibonacci_sphere(count: u32) -> impl Iterator<Item = Vec3> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+    (0..count).map(move |i| {
+        let z = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+        let radius = (1.0 - z * z).sqrt();
+        let angle = golden_angle * i as f32;
+        Vec3::new(radius * angle.cos(), radius * angle.sin(), z)
+    })
+}
+
+/// Scores the model stood with `up` pointing up, before weighting, turning
+/// it into `rotated`.
+fn measure(triangles: &[Triangle], up: Vec3, rotated: &mut Vec<Triangle>) -> OrientationCandidate {
+    rotated.clear();
+    rotated.extend_from_slice(triangles);
+    rotate(rotated, Quat::from_rotation_arc(up, Vec3::Z));
+    let rotated = &rotated[..];
+
+    let (min, max) = rotated.iter().fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), tri| {
+        (min.min(tri.v0).min(tri.v1).min(tri.v2), max.max(tri.v0).max(tri.v1).max(tri.v2))
+    });
+    let size = max - min;
+    let cell = (size.max_element() / RESOLUTION).max(1e-3);
+
+    let overhang_area_mm2 = rotated
+        .iter()
+        .filter(|tri| tri.v0.z.max(tri.v1.z).max(tri.v2.z) - min.z > cell)
+        .map(|tri| (tri.v1 - tri.v0).cross(tri.v2 - tri.v0))
+        .filter(|normal| -normal.z > normal.length() * std::f32::consts::FRAC_1_SQRT_2)
+        .fold(0.0, |area, normal| area + normal.length() / 2.0);
+
+    // Solid spans of a vertical line through each cell.
+    let columns = Columns::new(rotated, cell);
+    let (width, height) = columns.size();
+    let spans: Vec<Vec<(f32, f32)>> = columns
+        .lattice()
+        .map(|(x, y)| {
+            let hits = columns.hits(rotated, x, y);
+            hits.chunks_exact(2).map(|pair| (pair[0].0, pair[1].0)).collect()
+        })
+        .collect();
+
+    let voxel_mm3 = (cell * cell * cell) as f64;
+    let mut sweep = CavitySweep::new();
+    let mut below: Option<Vec<bool>> = None;
+    let (mut max_cells, mut islands) = (0, 0);
+    for layer in 0..(size.z / cell).ceil() as usize {
+        let z = min.z + (layer as f32 + 0.5) * cell;
+        let solid: Vec<bool> = spans
+            .iter()
+            .map(|spans| spans.iter().any(|&(enter, exit)| enter <= z && z < exit))
+            .collect();
+        max_cells = max_cells.max(solid.iter().filter(|&&s| s).count());
+        sweep.push(EmptyRegions::label(&solid, width as u32, height as u32), voxel_mm3);
+        // The first layer with anything in it rests on the plate.
+        match &below {
+            Some(below) => islands += unsupported_regions(&solid, below, width, height).len(),
+            None if !solid.contains(&true) => continue,
+            None => {}
+        }
+        below = Some(solid);
+    }
+    let suction_volume_mm3 = sweep.finish().iter().fold(0.0, |volume, cavity| volume + cavity.volume_mm3) as f32;
+
+    OrientationCandidate {
+        up: up.to_array(),
+        score: 0.0,
+        overhang_area_mm2,
+        max_cross_section_mm2: max_cells as f32 * cell * cell,
+        islands: islands as u32,
+        height_mm: size.z,
+        suction_volume_mm3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cuboid;
+
+    /// A 20 x 20 mm plate 1 mm thick, stood on one of its long edges.
+    fn plate_on_edge() -> Vec<Triangle> {
+        cuboid(Vec3::ZERO, Vec3::new(20.0, 1.0, 20.0))
+    }
+
+    #[test]
+    fn lays_a_plate_on_its_large_face() {
+        // Weighted for a low print without supports, rather than a small
+        // cross-section.
+        let weights = OrientWeights { cross_section: 0.0, islands: 0.0, suction: 0.0, ..OrientWeights::default() };
+        let settings = AutoOrient { samples: 64, candidates: 1, weights };
+        let best = rank(&plate_on_edge(), &settings, &|| {})[0];
+
+        assert_eq!(Vec3::from_array(best.up).y.abs(), 1.0, "{}", best);
+        let mut turned = plate_on_edge();
+        rotate(&mut turned, rotation(&best));
+        let (low, high) = turned.iter().flat_map(|tri| [tri.v0.z, tri.v1.z, tri.v2.z]).fold(
+            (f32::MAX, f32::MIN),
+            |(low, high), z| (low.min(z), high.max(z)),
+        );
+        assert!((high - low - 1.0).abs() < 1e-4 && best.overhang_area_mm2 == 0.0, "{}", best);
+    }
+
+    #[test]
+    fn ranks_distinct_candidates_best_first() {
+        for candidates in [1, 5] {
+            let settings = AutoOrient { samples: 64, candidates, ..AutoOrient::default() };
+            let ranked = rank(&plate_on_edge(), &settings, &|| {});
+            assert_eq!(ranked.len(), candidates);
+            assert!(ranked.windows(2).all(|pair| pair[0].score <= pair[1].score));
+            for (i, a) in ranked.iter().enumerate() {
+                for b in &ranked[i + 1..] {
+                    let angle = Vec3::from_array(a.up).angle_between(Vec3::from_array(b.up)).to_degrees();
+                    assert!(angle >= MIN_SEPARATION_DEG, "{} and {} are {:.1}° apart", a, b, angle);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ranks_no_more_candidates_than_distinct_directions() {
+        // Only the model's axes, at least 90° apart.
+        let settings = AutoOrient { samples: 0, candidates: 10, ..AutoOrient::default() };
+        assert_eq!(rank(&plate_on_edge(), &settings, &|| {}).len(), 6);
+    }
+}
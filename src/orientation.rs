@@ -0,0 +1,133 @@
+//! Mirroring and rotating layers to suit how a printer's LCD is mounted.
+
+use image::GrayImage;
+use serde::Serialize;
+use std::fmt;
+
+/// Clockwise rotation of the output images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    pub const ALL: [Rotation; 4] = [Rotation::None, Rotation::Cw90, Rotation::Cw180, Rotation::Cw270];
+}
+
+impl fmt::Display for Rotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rotation::None => write!(f, "0°"),
+            Rotation::Cw90 => write!(f, "90°"),
+            Rotation::Cw180 => write!(f, "180°"),
+            Rotation::Cw270 => write!(f, "270°"),
+        }
+    }
+}
+
+/// How layers are laid out on the printer's screen. Images are mirrored
+/// first, then rotated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct Orientation {
+    /// Flip left to right.
+    pub mirror_x: bool,
+    /// Flip top to bottom.
+    pub mirror_y: bool,
+    pub rotation: Rotation,
+}
+
+impl Orientation {
+    pub(crate) fn is_identity(&self) -> bool {
+        *self == Orientation::default()
+    }
+
+    /// Whether the output's width runs along the model's Y axis.
+    pub(crate) fn swaps_axes(&self) -> bool {
+        matches!(self.rotation, Rotation::Cw90 | Rotation::Cw270)
+    }
+
+    /// Size of the output for an input of `size`, in any unit.
+    pub(crate) fn size<T>(&self, (width, height): (T, T)) -> (T, T) {
+        if self.swaps_axes() { (height, width) } else { (width, height) }
+    }
+
+    /// Moves the point `(x, y)` of an input spanning `0..width` and
+    /// `0..height` to where it lands in the output.
+    pub(crate) fn map(&self, (x, y): (f32, f32), (width, height): (f32, f32)) -> (f32, f32) {
+        let x = if self.mirror_x { width - x } else { x };
+        let y = if self.mirror_y { height - y } else { y };
+        match self.rotation {
+            Rotation::None => (x, y),
+            Rotation::Cw90 => (height - y, x),
+            Rotation::Cw180 => (width - x, height - y),
+            Rotation::Cw270 => (y, width - x),
+        }
+    }
+
+    /// Returns `img` mirrored and rotated.
+    pub(crate) fn apply(&self, img: GrayImage) -> GrayImage {
+        if self.is_identity() {
+            return img;
+        }
+        let (width, height) = self.size(img.dimensions());
+        let mut out = GrayImage::new(width, height);
+        let input_size = (img.width() as f32, img.height() as f32);
+        for (x, y, pixel) in img.enumerate_pixels() {
+            let (u, v) = self.map((x as f32 + 0.5, y as f32 + 0.5), input_size);
+            out.put_pixel(u as u32, v as u32, *pixel);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pixels of a 3 x 2 image numbered row by row from 1.
+    fn numbered() -> GrayImage {
+        GrayImage::from_fn(3, 2, |x, y| image::Luma([(y * 3 + x + 1) as u8]))
+    }
+
+    fn rows(img: &GrayImage) -> Vec<Vec<u8>> {
+        img.rows().map(|row| row.map(|p| p.0[0]).collect()).collect()
+    }
+
+    fn oriented(mirror_x: bool, mirror_y: bool, rotation: Rotation) -> Vec<Vec<u8>> {
+        rows(&Orientation { mirror_x, mirror_y, rotation }.apply(numbered()))
+    }
+
+    #[test]
+    fn rotates_clockwise() {
+        assert_eq!(oriented(false, false, Rotation::None), [[1, 2, 3], [4, 5, 6]]);
+        assert_eq!(oriented(false, false, Rotation::Cw90), [[4, 1], [5, 2], [6, 3]]);
+        assert_eq!(oriented(false, false, Rotation::Cw180), [[6, 5, 4], [3, 2, 1]]);
+        assert_eq!(oriented(false, false, Rotation::Cw270), [[3, 6], [2, 5], [1, 4]]);
+    }
+
+    #[test]
+    fn mirrors_before_rotating() {
+        assert_eq!(oriented(true, false, Rotation::None), [[3, 2, 1], [6, 5, 4]]);
+        assert_eq!(oriented(false, true, Rotation::None), [[4, 5, 6], [1, 2, 3]]);
+        assert_eq!(oriented(false, true, Rotation::Cw90), [[1, 4], [2, 5], [3, 6]]);
+        assert_eq!(oriented(true, true, Rotation::Cw180), [[1, 2, 3], [4, 5, 6]]);
+    }
+
+    #[test]
+    fn maps_points_like_pixels() {
+        // The top left corner of a 3 x 2 input, and the point one pixel right of it.
+        let map = |mirror_x, rotation, point| {
+            Orientation { mirror_x, mirror_y: false, rotation }.map(point, (3.0, 2.0))
+        };
+        assert_eq!(map(false, Rotation::Cw90, (0.0, 0.0)), (2.0, 0.0));
+        assert_eq!(map(false, Rotation::Cw90, (1.0, 0.0)), (2.0, 1.0));
+        assert_eq!(map(false, Rotation::Cw270, (1.0, 0.0)), (0.0, 2.0));
+        assert_eq!(map(true, Rotation::None, (1.0, 0.0)), (2.0, 0.0));
+        assert_eq!(map(true, Rotation::Cw180, (1.0, 0.0)), (1.0, 2.0));
+    }
+}
@@ -0,0 +1,363 @@
+//! Encoding and saving rendered layer images.
+
+use crate::layers::Layer;
+use crate::analysis::{Findings, LayerAnalysis};
+use crate::drain::DrainHole;
+use crate::raft::RaftImage;
+use crate::{compensation, drain, group4, layer_file_name, manifest, LayerInfo, PlateGrid, SliceError, SlicerConfig};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use tiff::encoder::{Rational, TiffEncoder};
+use tiff::tags::Tag;
+
+/// File format of the layer images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerFormat {
+    /// 8-bit grayscale PNG, which every printer's tools read.
+    #[default]
+    Png,
+    /// 1-bit PNG. Layers are not anti-aliased, so this loses nothing over
+    /// grayscale, but not every printer's tools read it.
+    BilevelPng,
+    /// One 1-bit TIFF per layer, compressed with CCITT Group 4.
+    Tiff,
+    /// Every layer as one page of a single Group 4 `layers.tif`, page `n`
+    /// holding layer index `n`.
+    TiffStack,
+}
+
+impl LayerFormat {
+    pub const ALL: [LayerFormat; 4] = [
+        LayerFormat::Png,
+        LayerFormat::BilevelPng,
+        LayerFormat::Tiff,
+        LayerFormat::TiffStack,
+    ];
+
+    /// Extension of each layer's own file, or `None` if layers share one.
+    pub(crate) fn extension(self) -> Option<&'static str> {
+        match self {
+            LayerFormat::Png | LayerFormat::BilevelPng => Some("png"),
+            LayerFormat::Tiff => Some("tif"),
+            LayerFormat::TiffStack => None,
+        }
+    }
+}
+
+impl fmt::Display for LayerFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayerFormat::Png => write!(f, "PNG (8-bit)"),
+            LayerFormat::BilevelPng => write!(f, "PNG (1-bit)"),
+            LayerFormat::Tiff => write!(f, "TIFF (Group 4)"),
+            LayerFormat::TiffStack => write!(f, "TIFF Stack"),
+        }
+    }
+}
+
+/// How hard PNG layers are compressed. TIFF layers always use Group 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PngCompression {
+    /// Quickest to write, for when disk space does not matter.
+    Fast,
+    #[default]
+    Balanced,
+    /// Smallest files, noticeably slower to write.
+    Best,
+}
+
+impl PngCompression {
+    pub const ALL: [PngCompression; 3] =
+        [PngCompression::Fast, PngCompression::Balanced, PngCompression::Best];
+}
+
+impl fmt::Display for PngCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PngCompression::Fast => write!(f, "Fast"),
+            PngCompression::Balanced => write!(f, "Balanced"),
+            PngCompression::Best => write!(f, "Best"),
+        }
+    }
+}
+
+/// File name of the multi-page TIFF written by [`LayerFormat::TiffStack`].
+pub(crate) const STACK_FILE_NAME: &str = "layers.tif";
+
+/// Saves layers in the configured format as the engines finish them.
+pub(crate) struct LayerWriter<'a> {
+    config: &'a SlicerConfig,
+    grid: &'a PlateGrid,
+    /// The TIFF stack being written, if layers go into one.
+    stack: Option<Mutex<TiffStack>>,
+    drain_holes: Vec<DrainHole>,
+    raft: Option<RaftImage>,
+    analysis: Option<LayerAnalysis<'a>>,
+}
+
+impl<'a> LayerWriter<'a> {
+    pub(crate) fn new(
+        config: &'a SlicerConfig,
+        grid: &'a PlateGrid,
+        layer_count: usize,
+        drain_holes: Vec<DrainHole>,
+        raft: Option<RaftImage>,
+    ) -> Result<Self, SliceError> {
+        let analysis = LayerAnalysis::new(config, grid)?;
+        let mut writer = Self { config, grid, stack: None, drain_holes, raft, analysis };
+        if config.layer_format == LayerFormat::TiffStack {
+            let path = Path::new(&config.output_dir).join(STACK_FILE_NAME);
+            let size = config.orientation.size((grid.width_px, grid.height_px));
+            let stack = TiffStack::create(&path, size, writer.pixel_size_mm(), layer_count)?;
+            writer.stack = Some(Mutex::new(stack));
+        }
+        Ok(writer)
+    }
+
+    /// Drain holes punched through every layer, on the mesh as sliced.
+    pub(crate) fn drain_holes(&self) -> &[DrainHole] {
+        &self.drain_holes
+    }
+
+    /// The raft drawn under the model, if there is one.
+    pub(crate) fn raft(&self) -> Option<&RaftImage> {
+        self.raft.as_ref()
+    }
+
+    pub(crate) fn add_drain_holes(&mut self, holes: Vec<DrainHole>) {
+        self.drain_holes.extend(holes);
+    }
+
+    /// Corrects a rendered layer, draws the raft into it, punches the drain
+    /// holes, measures and checks it and saves it in the printer's
+    /// orientation. The raft is drawn after correction so offsets and
+    /// elephant-foot compensation leave it as sized.
+    pub(crate) fn write(&self, layer: &Layer, mut img: image::GrayImage) -> Result<LayerInfo, SliceError> {
+        compensation::apply(self.config, self.grid, layer, &mut img);
+        if let Some(raft) = &self.raft {
+            raft.draw(layer, &mut img);
+        }
+        let height = img.height();
+        drain::punch(&self.drain_holes, self.grid, layer.z, |x, y| img.put_pixel(x, height - 1 - y, image::Luma([0])));
+        let file = match self.config.layer_format.extension() {
+            Some(extension) => layer_file_name(layer, extension),
+            None => STACK_FILE_NAME.to_string(),
+        };
+        let info = manifest::layer_info(self.grid, layer, file, &img);
+        if let Some(analysis) = &self.analysis {
+            analysis.add(layer, &img)?;
+        }
+
+        let img = self.config.orientation.apply(img);
+        let path = Path::new(&self.config.output_dir).join(&info.file);
+        let pixel_size_mm = self.pixel_size_mm();
+        match self.config.layer_format {
+            LayerFormat::Png => save_gray_png(&path, pixel_size_mm, &img, self.config.png_compression)?,
+            LayerFormat::BilevelPng => save_bilevel_png(&path, pixel_size_mm, &img, self.config.png_compression)?,
+            LayerFormat::Tiff => save_tiff(&path, img.dimensions(), pixel_size_mm, &group4::encode(&img))?,
+            LayerFormat::TiffStack => {
+                let page = group4::encode(&img);
+                if let Some(stack) = &self.stack {
+                    stack.lock().unwrap().add(layer.index, page)?;
+                }
+            }
+        }
+        Ok(info)
+    }
+
+    /// Width and height of an output pixel.
+    fn pixel_size_mm(&self) -> (f32, f32) {
+        self.config.orientation.size((self.grid.pixel_size_x_mm, self.grid.pixel_size_y_mm))
+    }
+
+    /// Closes the TIFF stack and ends the layer checks once every layer has
+    /// been saved.
+    pub(crate) fn finish(self, layers: &[Layer]) -> Findings {
+        if let Some(stack) = self.stack {
+            let stack = stack.into_inner().unwrap();
+            debug_assert_eq!(stack.next, stack.total, "every page of the stack is written");
+        }
+        self.analysis.map(|analysis| analysis.finish(layers)).unwrap_or_default()
+    }
+}
+
+/// A multi-page TIFF written a page at a time in layer order. Layers finish
+/// out of order, so each page is held until every page before it is written,
+/// and engines render in chunks of ordered layers, which keeps the number
+/// held small.
+struct TiffStack {
+    encoder: TiffEncoder<BufWriter<File>>,
+    size: (u32, u32),
+    pixel_size_mm: (f32, f32),
+    /// Index of the next page to write.
+    next: u32,
+    total: u32,
+    /// Encoded pages waiting for the ones before them.
+    pending: BTreeMap<u32, Vec<u8>>,
+}
+
+impl TiffStack {
+    fn create(path: &Path, size: (u32, u32), pixel_size_mm: (f32, f32), total: usize) -> Result<Self, SliceError> {
+        let file = File::create(path).map_err(SliceError::io("Could not save TIFF"))?;
+        let encoder = TiffEncoder::new(BufWriter::new(file)).map_err(tiff_error)?;
+        let total = u32::try_from(total).unwrap_or(u32::MAX);
+        Ok(Self { encoder, size, pixel_size_mm, next: 0, total, pending: BTreeMap::new() })
+    }
+
+    /// Adds the encoded page of layer `index`, writing it and any pages
+    /// after it that were waiting for it.
+    fn add(&mut self, index: u32, page: Vec<u8>) -> Result<(), SliceError> {
+        self.pending.insert(index, page);
+        while let Some(page) = self.pending.remove(&self.next) {
+            let number = (self.total > 1).then_some((self.next, self.total));
+            write_page(&mut self.encoder, self.size, self.pixel_size_mm, &page, number)?;
+            self.next += 1;
+        }
+        Ok(())
+    }
+}
+
+fn save_gray_png(
+    path: &Path,
+    pixel_size_mm: (f32, f32),
+    img: &image::GrayImage,
+    compression: PngCompression,
+) -> Result<(), SliceError> {
+    let mut encoder = png_encoder(path, pixel_size_mm, img)?;
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_compression(match compression {
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Balanced => png::Compression::Default,
+        PngCompression::Best => png::Compression::Best,
+    });
+    encoder.set_filter(png::FilterType::Sub);
+    encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+    write_png(encoder, img.as_raw())
+}
+
+/// Saves a 1-bit PNG where nonzero pixels are white.
+fn save_bilevel_png(
+    path: &Path,
+    pixel_size_mm: (f32, f32),
+    img: &image::GrayImage,
+    compression: PngCompression,
+) -> Result<(), SliceError> {
+    let row_bytes = img.width().div_ceil(8) as usize;
+    let mut packed = vec![0u8; row_bytes * img.height() as usize];
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if pixel.0[0] != 0 {
+            packed[y as usize * row_bytes + x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+
+    let mut encoder = png_encoder(path, pixel_size_mm, img)?;
+    encoder.set_depth(png::BitDepth::One);
+    // Filtering packed bits mostly hurts, except that the fast compressor
+    // needs it to find runs at all.
+    match compression {
+        PngCompression::Fast => {
+            encoder.set_compression(png::Compression::Fast);
+            encoder.set_adaptive_filter(png::AdaptiveFilterType::Adaptive);
+        }
+        PngCompression::Balanced => {
+            encoder.set_compression(png::Compression::Default);
+            encoder.set_filter(png::FilterType::NoFilter);
+        }
+        PngCompression::Best => {
+            encoder.set_compression(png::Compression::Best);
+            encoder.set_filter(png::FilterType::NoFilter);
+        }
+    }
+    write_png(encoder, &packed)
+}
+
+/// Starts a grayscale PNG tagged with the physical pixel size, so viewers
+/// can show non-square pixels at the right aspect ratio.
+fn png_encoder(
+    path: &Path,
+    pixel_size_mm: (f32, f32),
+    img: &image::GrayImage,
+) -> Result<png::Encoder<'static, BufWriter<File>>, SliceError> {
+    let file = File::create(path).map_err(SliceError::io("Could not save image"))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), img.width(), img.height());
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_pixel_dims(Some(png::PixelDimensions {
+        xppu: (1000.0 / pixel_size_mm.0).round() as u32,
+        yppu: (1000.0 / pixel_size_mm.1).round() as u32,
+        unit: png::Unit::Meter,
+    }));
+    Ok(encoder)
+}
+
+fn write_png(encoder: png::Encoder<'_, BufWriter<File>>, data: &[u8]) -> Result<(), SliceError> {
+    encoder
+        .write_header()
+        .and_then(|mut writer| {
+            writer.write_image_data(data)?;
+            writer.finish()
+        })
+        .map_err(|e| SliceError::io("Could not save image")(e.into()))
+}
+
+/// Saves a Group 4 encoded page as a TIFF.
+fn save_tiff(path: &Path, size: (u32, u32), pixel_size_mm: (f32, f32), page: &[u8]) -> Result<(), SliceError> {
+    let file = File::create(path).map_err(SliceError::io("Could not save TIFF"))?;
+    let mut tiff = TiffEncoder::new(BufWriter::new(file)).map_err(tiff_error)?;
+    write_page(&mut tiff, size, pixel_size_mm, page, None)
+}
+
+/// Writes a Group 4 encoded page, numbered `(page, total)` if it is part of
+/// a multi-page document. Pixels are tagged with their physical size so
+/// viewers show true scale.
+fn write_page<W: Write + Seek>(
+    tiff: &mut TiffEncoder<W>,
+    (width, height): (u32, u32),
+    pixel_size_mm: (f32, f32),
+    page: &[u8],
+    number: Option<(u32, u32)>,
+) -> Result<(), SliceError> {
+    let pixels_per_cm = |pixel_size_mm: f32| Rational { n: (10_000.0 / pixel_size_mm).round() as u32, d: 1000 };
+    let mut dir = tiff.new_directory().map_err(tiff_error)?;
+    let offset = dir.write_data(page).map_err(tiff_error)?;
+    let mut tags = || -> tiff::TiffResult<()> {
+        if let Some((number, total)) = number {
+            // Bit 1 marks a page of a multi-page document.
+            dir.write_tag(Tag::NewSubfileType, 2u32)?;
+            let number = u16::try_from(number).unwrap_or(u16::MAX);
+            let total = u16::try_from(total).unwrap_or(u16::MAX);
+            dir.write_tag(Tag::Unknown(PAGE_NUMBER), &[number, total][..])?;
+        }
+        dir.write_tag(Tag::ImageWidth, width)?;
+        dir.write_tag(Tag::ImageLength, height)?;
+        dir.write_tag(Tag::BitsPerSample, 1u16)?;
+        dir.write_tag(Tag::Compression, COMPRESSION_GROUP4)?;
+        dir.write_tag(Tag::PhotometricInterpretation, PHOTOMETRIC_BLACK_IS_ZERO)?;
+        dir.write_tag(Tag::StripOffsets, offset as u32)?;
+        dir.write_tag(Tag::SamplesPerPixel, 1u16)?;
+        dir.write_tag(Tag::RowsPerStrip, height)?;
+        dir.write_tag(Tag::StripByteCounts, page.len() as u32)?;
+        dir.write_tag(Tag::XResolution, pixels_per_cm(pixel_size_mm.0))?;
+        dir.write_tag(Tag::YResolution, pixels_per_cm(pixel_size_mm.1))?;
+        dir.write_tag(Tag::Unknown(T6_OPTIONS), 0u32)?;
+        dir.write_tag(Tag::ResolutionUnit, RESOLUTION_UNIT_CM)
+    };
+    tags().map_err(tiff_error)?;
+    dir.finish().map_err(tiff_error)
+}
+
+fn tiff_error(e: tiff::TiffError) -> SliceError {
+    SliceError::io("Could not save TIFF")(io::Error::other(e))
+}
+
+const COMPRESSION_GROUP4: u16 = 4;
+const PHOTOMETRIC_BLACK_IS_ZERO: u16 = 1;
+const RESOLUTION_UNIT_CM: u16 = 3;
+const T6_OPTIONS: u16 = 293;
+const PAGE_NUMBER: u16 = 297;
@@ -0,0 +1,181 @@
+//! The cross-section profile: how much of each layer is lit, which sets
+//! how hard the layer pulls on the vat film as it peels away.
+
+use crate::{LayerInfo, SliceError, SlicerConfig};
+use serde::Serialize;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+pub(crate) const CSV_FILE_NAME: &str = "area_profile.csv";
+pub(crate) const JSON_FILE_NAME: &str = "area_profile.json";
+
+/// Export of the per-layer cross-section area, written as
+/// `area_profile.csv` and `area_profile.json` in the output directory.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AreaProfile {
+    /// Flag layers whose area grows by more than this over the layer below,
+    /// in square millimeters.
+    pub jump_threshold_mm2: f32,
+}
+
+impl Default for AreaProfile {
+    fn default() -> Self {
+        Self { jump_threshold_mm2: 100.0 }
+    }
+}
+
+/// Cross-section of one layer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LayerArea {
+    pub index: u32,
+    pub z_mm: f32,
+    pub area_mm2: f32,
+    /// Change in area from the layer below, or the whole area for the
+    /// first layer.
+    pub delta_mm2: f32,
+    pub perimeter_mm: f32,
+    /// Whether the area grows by more than the jump threshold. The first
+    /// layer sticks to the plate and is never flagged.
+    pub jump: bool,
+}
+
+impl fmt::Display for LayerArea {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cross-section grows by {:.1} mm² to {:.1} mm² in layer {} (Z {:.3} mm)",
+            self.delta_mm2, self.area_mm2, self.index, self.z_mm
+        )
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    match config.area_profile {
+        Some(profile) if !(profile.jump_threshold_mm2.is_finite() && profile.jump_threshold_mm2 >= 0.0) => Err(
+            SliceError::InvalidConfig("area jump threshold must not be negative".to_string()),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Builds the profile of the written layers, in print order.
+pub fn area_profile(layers: &[LayerInfo], settings: &AreaProfile) -> Vec<LayerArea> {
+    let mut below = None;
+    layers
+        .iter()
+        .map(|layer| {
+            let delta_mm2 = layer.area_mm2 - below.unwrap_or(0.0);
+            let jump = below.is_some() && delta_mm2 > settings.jump_threshold_mm2;
+            below = Some(layer.area_mm2);
+            LayerArea {
+                index: layer.index,
+                z_mm: layer.z_mm,
+                area_mm2: layer.area_mm2,
+                delta_mm2,
+                perimeter_mm: layer.perimeter_mm,
+                jump,
+            }
+        })
+        .collect()
+}
+
+/// Writes the profile as CSV and JSON to the output directory.
+pub(crate) fn write(config: &SlicerConfig, profile: &[LayerArea]) -> Result<(), SliceError> {
+    let dir = Path::new(&config.output_dir);
+    let context = "Could not write area profile";
+
+    let file = File::create(dir.join(CSV_FILE_NAME)).map_err(SliceError::io(context))?;
+    let mut csv = BufWriter::new(file);
+    let rows = std::iter::once("index,z_mm,area_mm2,delta_mm2,perimeter_mm,jump".to_string()).chain(
+        profile.iter().map(|layer| {
+            format!(
+                "{},{:.4},{:.4},{:.4},{:.4},{}",
+                layer.index, layer.z_mm, layer.area_mm2, layer.delta_mm2, layer.perimeter_mm, layer.jump
+            )
+        }),
+    );
+    for row in rows {
+        writeln!(csv, "{}", row).map_err(SliceError::io(context))?;
+    }
+    csv.flush().map_err(SliceError::io(context))?;
+
+    let file = File::create(dir.join(JSON_FILE_NAME)).map_err(SliceError::io(context))?;
+    serde_json::to_writer_pretty(BufWriter::new(file), profile).map_err(|e| SliceError::io(context)(e.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(areas: &[f32]) -> Vec<LayerInfo> {
+        areas
+            .iter()
+            .enumerate()
+            .map(|(index, &area_mm2)| LayerInfo {
+                index: index as u32,
+                z_mm: index as f32 * 0.05,
+                thickness_mm: 0.05,
+                file: format!("{}.png", index),
+                lit_pixels: 0,
+                area_mm2,
+                perimeter_mm: 0.0,
+                bbox_px: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_change_from_the_layer_below() {
+        let profile = area_profile(&layers(&[40.0, 40.0, 65.0, 10.0]), &AreaProfile::default());
+        let deltas: Vec<f32> = profile.iter().map(|layer| layer.delta_mm2).collect();
+        assert_eq!(deltas, [40.0, 0.0, 25.0, -55.0]);
+    }
+
+    #[test]
+    fn flags_growth_beyond_the_threshold() {
+        let settings = AreaProfile { jump_threshold_mm2: 20.0 };
+        let profile = area_profile(&layers(&[10.0, 30.0, 50.5, 30.0, 70.0]), &settings);
+        let jumps: Vec<bool> = profile.iter().map(|layer| layer.jump).collect();
+        // Growing by exactly the threshold is not a jump, nor is shrinking.
+        assert_eq!(jumps, [false, false, true, false, true]);
+    }
+
+    #[test]
+    fn never_flags_the_first_layer() {
+        let profile = area_profile(&layers(&[500.0, 500.0]), &AreaProfile { jump_threshold_mm2: 0.0 });
+        assert_eq!(profile[0].delta_mm2, 500.0);
+        assert!(!profile[0].jump && !profile[1].jump);
+    }
+
+    #[test]
+    fn writes_csv_and_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = SlicerConfig { output_dir: dir.path().to_str().unwrap().to_string(), ..SlicerConfig::default() };
+        let profile = area_profile(&layers(&[1.5, 120.0]), &AreaProfile::default());
+        write(&config, &profile).unwrap();
+
+        let csv = std::fs::read_to_string(dir.path().join(CSV_FILE_NAME)).unwrap();
+        let rows: Vec<&str> = csv.lines().collect();
+        assert_eq!(rows, [
+            "index,z_mm,area_mm2,delta_mm2,perimeter_mm,jump",
+            "0,0.0000,1.5000,1.5000,0.0000,false",
+            "1,0.0500,120.0000,118.5000,0.0000,true",
+        ]);
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join(JSON_FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(json[1]["jump"], true);
+    }
+
+    #[test]
+    fn rejects_negative_thresholds() {
+        let config = |jump_threshold_mm2| SlicerConfig {
+            area_profile: Some(AreaProfile { jump_threshold_mm2 }),
+            ..SlicerConfig::default()
+        };
+        assert!(validate(&config(-1.0)).is_err());
+        assert!(validate(&config(f32::NAN)).is_err());
+        assert!(validate(&config(0.0)).is_ok());
+    }
+}
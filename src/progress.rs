@@ -0,0 +1,136 @@
+use crate::SliceReport;
+use std::fmt;
+use std::sync::mpsc::{Sender, SyncSender};
+
+/// A progress update emitted by the slicer as it moves through its phases.
+#[derive(Debug, Clone)]
+pub enum ProgressEvent {
+    /// Reading and parsing the input STL.
+    Loading,
+    /// Scoring orientations for auto-orient; `done` counts directions tried.
+    Orienting { done: u32, total: u32 },
+    /// Building the bounding volume hierarchy over `triangles` faces.
+    BuildingBvh { triangles: usize },
+    /// Casting rays through the XY grid, one pixel row at a time.
+    Raytracing { rows_done: u32, rows_total: u32 },
+    /// Rendering and saving layer images; `layer` counts completed layers.
+    Rendering { layer: u32, total: u32 },
+    /// Preparing the output directory.
+    Writing,
+    /// Writing the SVG contours, manifest and other files once every layer
+    /// has been saved.
+    Finishing,
+    Finished(SliceReport),
+    Failed(String),
+}
+
+impl ProgressEvent {
+    /// Fraction of the current phase that is complete, if the phase is measurable.
+    pub fn phase_fraction(&self) -> Option<f32> {
+        match self {
+            ProgressEvent::Raytracing { rows_done, rows_total } => {
+                Some(ratio(*rows_done, *rows_total))
+            }
+            ProgressEvent::Orienting { done, total } => Some(ratio(*done, *total)),
+            ProgressEvent::Rendering { layer, total } => Some(ratio(*layer, *total)),
+            ProgressEvent::Finished(_) => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// Approximate fraction of the whole job that is complete.
+    ///
+    /// Phases are weighted by their typical share of the run time so a single
+    /// bar can still be drawn from the event stream.
+    pub fn overall_fraction(&self) -> f32 {
+        match self {
+            ProgressEvent::Loading => 0.0,
+            ProgressEvent::Orienting { done, total } => ratio(*done, *total) * 0.05,
+            ProgressEvent::BuildingBvh { .. } => 0.05,
+            ProgressEvent::Raytracing { rows_done, rows_total } => {
+                0.1 + ratio(*rows_done, *rows_total) * 0.4
+            }
+            ProgressEvent::Writing => 0.5,
+            ProgressEvent::Rendering { layer, total } => 0.5 + ratio(*layer, *total) * 0.5,
+            ProgressEvent::Finishing | ProgressEvent::Finished(_) | ProgressEvent::Failed(_) => 1.0,
+        }
+    }
+
+    /// Whether this event ends the job.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ProgressEvent::Finished(_) | ProgressEvent::Failed(_))
+    }
+}
+
+impl fmt::Display for ProgressEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressEvent::Loading => write!(f, "Loading STL..."),
+            ProgressEvent::Orienting { done, total } => {
+                write!(f, "Scoring orientation {} of {}", done, total)
+            }
+            ProgressEvent::BuildingBvh { triangles } => {
+                write!(f, "Building BVH over {} triangles...", triangles)
+            }
+            ProgressEvent::Raytracing { rows_done, rows_total } => {
+                write!(f, "Raytracing row {} of {}", rows_done, rows_total)
+            }
+            ProgressEvent::Rendering { layer, total } => {
+                write!(f, "Processing layer {} of {}", layer, total)
+            }
+            ProgressEvent::Writing => write!(f, "Preparing output..."),
+            ProgressEvent::Finishing => write!(f, "Writing output files..."),
+            ProgressEvent::Finished(report) => {
+                write!(f, "Done! {} layers written", report.layers.len())
+            }
+            ProgressEvent::Failed(error) => write!(f, "Failed: {}", error),
+        }
+    }
+}
+
+fn ratio(done: u32, total: u32) -> f32 {
+    if total == 0 {
+        1.0
+    } else {
+        done as f32 / total as f32
+    }
+}
+
+/// Receiver of progress events from the slicer.
+///
+/// The slicer reports from worker threads, so sinks must be `Sync`. Channel
+/// senders, closures and [`NoProgress`] implement this out of the box; wrap a
+/// C callback or an async channel in a closure to forward events elsewhere.
+pub trait ProgressSink: Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+/// A sink that discards every event.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn report(&self, _event: ProgressEvent) {}
+}
+
+impl ProgressSink for Sender<ProgressEvent> {
+    fn report(&self, event: ProgressEvent) {
+        // A dropped receiver just means nobody is listening any more.
+        let _ = self.send(event);
+    }
+}
+
+impl ProgressSink for SyncSender<ProgressEvent> {
+    fn report(&self, event: ProgressEvent) {
+        let _ = self.send(event);
+    }
+}
+
+impl<F> ProgressSink for F
+where
+    F: Fn(ProgressEvent) + Sync,
+{
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
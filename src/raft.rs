@@ -0,0 +1,370 @@
+//! A raft drawn into the bottom layers for the model to stand on.
+//!
+//! The raft follows the convex hull of the mesh where it meets the plate,
+//! grown by the margin, so every support foot and part of the model
+//! touching the plate shares one base.
+
+use crate::layers::Layer;
+use crate::{PlateGrid, SliceError, SlicerConfig, Triangle};
+use glam::{Vec2, Vec3};
+use image::Luma;
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// A base plate under the model. Sizes are in micrometers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Raft {
+    /// Height of the raft, which the model is lifted by.
+    pub thickness_um: f32,
+    /// How far the raft reaches past the model's footprint at the plate.
+    pub margin_um: f32,
+    /// How far the edge slopes in from the bottom of the raft to the top.
+    pub chamfer_um: f32,
+    /// Break the part of the raft outside the footprint into lines, so a
+    /// scraper gets under it easily.
+    pub crenelation: Option<Crenelation>,
+}
+
+impl Default for Raft {
+    fn default() -> Self {
+        Self {
+            thickness_um: 1000.0,
+            margin_um: 2000.0,
+            chamfer_um: 1000.0,
+            crenelation: None,
+        }
+    }
+}
+
+/// Lines across the edge of the raft, square to its outline.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Crenelation {
+    pub line_width_um: f32,
+    pub gap_um: f32,
+}
+
+impl Default for Crenelation {
+    fn default() -> Self {
+        Self { line_width_um: 500.0, gap_um: 500.0 }
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    let Some(raft) = config.raft else {
+        return Ok(());
+    };
+    let positive = |v: f32| v.is_finite() && v > 0.0;
+    if !positive(raft.thickness_um) {
+        return Err(SliceError::InvalidConfig("raft thickness must be positive".to_string()));
+    }
+    let sizes = [raft.margin_um, raft.chamfer_um];
+    if !sizes.iter().all(|size| size.is_finite() && *size >= 0.0) || raft.chamfer_um > raft.margin_um {
+        return Err(SliceError::InvalidConfig(
+            "raft margin must not be negative, and the chamfer must lie within it".to_string(),
+        ));
+    }
+    if let Some(crenelation) = raft.crenelation
+        && !(positive(crenelation.line_width_um) && positive(crenelation.gap_um))
+    {
+        return Err(SliceError::InvalidConfig("raft line width and gap must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// The raft's shape in model millimeters.
+pub(crate) struct RaftOutline {
+    raft: Raft,
+    /// Counter-clockwise convex hull of the footprint, which may also be a
+    /// single point or segment.
+    hull: Vec<Vec2>,
+    bottom_z: f32,
+}
+
+impl RaftOutline {
+    /// Lifts the mesh by the raft's thickness and outlines the raft under
+    /// the part of it within `band_mm` of the bottom.
+    pub(crate) fn lift(raft: &Raft, triangles: &mut [Triangle], band_mm: f32) -> Self {
+        let bottom_z = triangles
+            .iter()
+            .flat_map(|tri| [tri.v0.z, tri.v1.z, tri.v2.z])
+            .fold(f32::MAX, f32::min);
+        let lift_mm = raft.thickness_um / 1000.0;
+        for tri in triangles.iter_mut() {
+            for v in [&mut tri.v0, &mut tri.v1, &mut tri.v2] {
+                v.z += lift_mm;
+            }
+        }
+
+        // Every vertex in the band, and every edge crossing its top.
+        let cut = bottom_z + lift_mm + band_mm;
+        let mut points = Vec::new();
+        for tri in triangles.iter() {
+            for (a, b) in [(tri.v0, tri.v1), (tri.v1, tri.v2), (tri.v2, tri.v0)] {
+                if a.z <= cut {
+                    points.push(a.truncate());
+                }
+                if (a.z < cut) != (b.z < cut) {
+                    points.push(a.lerp(b, (cut - a.z) / (b.z - a.z)).truncate());
+                }
+            }
+        }
+        Self { raft: *raft, hull: convex_hull(points), bottom_z }
+    }
+
+    pub(crate) fn lift_mm(&self) -> f32 {
+        self.raft.thickness_um / 1000.0
+    }
+
+    /// Corners of the box around the raft.
+    pub(crate) fn bounds(&self) -> (Vec3, Vec3) {
+        let margin = self.raft.margin_um / 1000.0;
+        let (min, max) = self
+            .hull
+            .iter()
+            .fold((Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)), |(min, max), p| (min.min(*p), max.max(*p)));
+        ((min - margin).extend(self.bottom_z), (max + margin).extend(self.bottom_z))
+    }
+
+    /// Measures every pixel of the plate near the raft against its outline.
+    pub(crate) fn rasterize(&self, grid: &PlateGrid) -> RaftImage {
+        let (min, max) = self.bounds();
+        let column = |v: f32, origin: f32, pitch: f32, count: u32| {
+            (((v - origin) / pitch - 0.5).max(0.0) as u32).min(count)
+        };
+        let x0 = column(min.x, grid.min_bound.x, grid.pixel_size_x_mm, grid.width_px);
+        let x1 = (column(max.x, grid.min_bound.x, grid.pixel_size_x_mm, grid.width_px) + 2).min(grid.width_px);
+        let y0 = column(min.y, grid.min_bound.y, grid.pixel_size_y_mm, grid.height_px);
+        let y1 = (column(max.y, grid.min_bound.y, grid.pixel_size_y_mm, grid.height_px) + 2).min(grid.height_px);
+
+        let lines = self.raft.crenelation.map(|c| (c.line_width_um / 1000.0, (c.line_width_um + c.gap_um) / 1000.0));
+        let distances = (y0..y1)
+            .into_par_iter()
+            .flat_map_iter(|y| {
+                (x0..x1).map(move |x| {
+                    let p = Vec2::new(
+                        grid.min_bound.x + (x as f32 + 0.5) * grid.pixel_size_x_mm,
+                        grid.min_bound.y + (y as f32 + 0.5) * grid.pixel_size_y_mm,
+                    );
+                    let (distance, along) = self.distance(p);
+                    match lines {
+                        Some((width, period)) if distance > 0.0 && along.rem_euclid(period) >= width => f32::INFINITY,
+                        _ => distance,
+                    }
+                })
+            })
+            .collect();
+        RaftImage {
+            columns: x0..x1,
+            rows: y0..y1,
+            distances,
+            bottom_z: self.bottom_z,
+            thickness_mm: self.lift_mm(),
+            margin_mm: self.raft.margin_um / 1000.0,
+            chamfer_mm: self.raft.chamfer_um / 1000.0,
+        }
+    }
+
+    /// Distance from `p` to the footprint, zero inside it, and how far
+    /// along the nearest edge `p` lies.
+    fn distance(&self, p: Vec2) -> (f32, f32) {
+        let hull = &self.hull;
+        let edges = (0..hull.len()).map(|i| (hull[i], hull[(i + 1) % hull.len()]));
+        let mut nearest = (f32::MAX, 0.0);
+        let mut inside = hull.len() >= 3;
+        for (a, b) in edges {
+            let edge = b - a;
+            inside &= edge.perp_dot(p - a) >= 0.0;
+            let length_sq = edge.length_squared();
+            let t = if length_sq > 0.0 { ((p - a).dot(edge) / length_sq).clamp(0.0, 1.0) } else { 0.0 };
+            let distance = p.distance(a + edge * t);
+            if distance < nearest.0 {
+                nearest = (distance, (p - a).dot(edge.normalize_or_zero()));
+            }
+        }
+        if inside { (0.0, nearest.1) } else { nearest }
+    }
+}
+
+/// The raft measured on the plate's pixels, ready to draw into layers.
+pub(crate) struct RaftImage {
+    columns: std::ops::Range<u32>,
+    rows: std::ops::Range<u32>,
+    /// Distance of each pixel in the box from the footprint, row by row,
+    /// or infinity between the lines of a crenelated edge.
+    distances: Vec<f32>,
+    bottom_z: f32,
+    thickness_mm: f32,
+    margin_mm: f32,
+    chamfer_mm: f32,
+}
+
+impl RaftImage {
+    /// Lights the raft's pixels in a layer image, whose row 0 is at the
+    /// plate's maximum Y.
+    pub(crate) fn draw(&self, layer: &Layer, img: &mut image::GrayImage) {
+        let height = img.height();
+        self.for_each_pixel(layer, |x, y| img.put_pixel(x, height - 1 - y, Luma([255])));
+    }
+
+    /// Marks the raft's pixels solid in a layer mask of the whole plate,
+    /// whose row 0 is at the plate's minimum Y.
+    pub(crate) fn fill(&self, layer: &Layer, solid: &mut [bool], width: u32) {
+        self.for_each_pixel(layer, |x, y| solid[(y * width + x) as usize] = true);
+    }
+
+    /// Height of the raft's underside, on the build plate.
+    pub(crate) fn bottom_z(&self) -> f32 {
+        self.bottom_z
+    }
+
+    /// Calls `f(x, y)` for each of the raft's pixels in a layer, in plate
+    /// pixel coordinates with row 0 at minimum Y.
+    fn for_each_pixel(&self, layer: &Layer, mut f: impl FnMut(u32, u32)) {
+        let height = (layer.z - self.bottom_z) / self.thickness_mm;
+        if !(0.0..1.0).contains(&height) {
+            return;
+        }
+        let reach = self.margin_mm - self.chamfer_mm * height;
+        let width = self.columns.len();
+        for (row, y) in self.rows.clone().enumerate() {
+            for (column, x) in self.columns.clone().enumerate() {
+                if self.distances[row * width + column] <= reach {
+                    f(x, y);
+                }
+            }
+        }
+    }
+}
+
+/// Counter-clockwise convex hull of `points` by Andrew's monotone chain.
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let mut hull: Vec<Vec2> = Vec::with_capacity(2 * points.len());
+    for pass in [points.as_slice(), &points.iter().rev().copied().collect::<Vec<_>>()] {
+        let start = hull.len();
+        for &p in pass {
+            while hull.len() >= start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                if (b - a).perp_dot(p - a) > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // The last point of each chain starts the other.
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grid of 0.1 mm pixels from `min` to `max`.
+    fn grid(min: Vec2, max: Vec2) -> PlateGrid {
+        PlateGrid {
+            min_bound: min.extend(0.0),
+            max_bound: max.extend(0.0),
+            pixel_size_x_mm: 0.1,
+            pixel_size_y_mm: 0.1,
+            width_px: ((max.x - min.x) / 0.1).round() as u32,
+            height_px: ((max.y - min.y) / 0.1).round() as u32,
+        }
+    }
+
+    /// Centers of the raft's pixels in the layer at `z`.
+    fn lit(image: &RaftImage, grid: &PlateGrid, z: f32) -> Vec<Vec2> {
+        let layer = Layer { index: 0, z, thickness_mm: 0.05, offset_um: z * 1000.0, name: String::new() };
+        let mut solid = vec![false; (grid.width_px * grid.height_px) as usize];
+        image.fill(&layer, &mut solid, grid.width_px);
+        let width = grid.width_px as usize;
+        (0..solid.len())
+            .filter(|&i| solid[i])
+            .map(|i| {
+                let (x, y) = ((i % width) as f32 + 0.5, (i / width) as f32 + 0.5);
+                grid.min_bound.truncate() + Vec2::new(x * grid.pixel_size_x_mm, y * grid.pixel_size_y_mm)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hull_of_a_square_drops_inner_points() {
+        let corners = [Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0), Vec2::new(4.0, 4.0), Vec2::new(0.0, 4.0)];
+        let inner = [Vec2::new(1.0, 1.0), Vec2::new(2.0, 3.0), Vec2::new(2.0, 0.0), Vec2::new(4.0, 2.0)];
+        let hull = convex_hull(inner.iter().chain(&corners).rev().copied().collect());
+        assert_eq!(hull.len(), 4);
+        assert!(corners.iter().all(|corner| hull.contains(corner)));
+        // Counter-clockwise: every corner turns left.
+        for i in 0..4 {
+            let (a, b, c) = (hull[i], hull[(i + 1) % 4], hull[(i + 2) % 4]);
+            assert!((b - a).perp_dot(c - a) > 0.0);
+        }
+    }
+
+    #[test]
+    fn hull_of_a_point_or_segment_stays_degenerate() {
+        let point = Vec2::new(1.0, 2.0);
+        assert_eq!(convex_hull(vec![point, point, point]), [point]);
+
+        let (a, b) = (Vec2::new(0.0, 0.0), Vec2::new(3.0, 3.0));
+        let hull = convex_hull(vec![b, Vec2::new(1.0, 1.0), a, Vec2::new(2.0, 2.0)]);
+        assert_eq!(hull, [a, b]);
+    }
+
+    #[test]
+    fn chamfer_narrows_the_raft_towards_its_top() {
+        // A 1 mm raft around a single point, 2 mm wide at the bottom and
+        // 1 mm at the top.
+        let raft = Raft { thickness_um: 1000.0, margin_um: 2000.0, chamfer_um: 1000.0, crenelation: None };
+        let outline = RaftOutline { raft, hull: vec![Vec2::ZERO], bottom_z: 0.0 };
+        let grid = grid(Vec2::splat(-3.0), Vec2::splat(3.0));
+        let image = outline.rasterize(&grid);
+        for (z, reach) in [(0.0, 2.0), (0.5, 1.5), (0.9, 1.1)] {
+            let reached = lit(&image, &grid, z).iter().map(|p| p.length()).fold(0.0, f32::max);
+            assert!(reached <= reach && reached > reach - 0.1, "reach {} at Z {}", reached, z);
+        }
+        assert!(lit(&image, &grid, 1.0).is_empty());
+        assert!(lit(&image, &grid, -0.1).is_empty());
+    }
+
+    #[test]
+    fn crenelation_breaks_only_the_edge_into_lines() {
+        // Lines 0.5 mm wide every 1 mm along each edge of a 10 mm square.
+        let raft = Raft {
+            thickness_um: 1000.0,
+            margin_um: 2000.0,
+            chamfer_um: 0.0,
+            crenelation: Some(Crenelation { line_width_um: 500.0, gap_um: 500.0 }),
+        };
+        let hull = convex_hull(vec![Vec2::ZERO, Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(0.0, 10.0)]);
+        let outline = RaftOutline { raft, hull, bottom_z: 0.0 };
+        let grid = grid(Vec2::splat(-3.0), Vec2::splat(13.0));
+        let pixels = lit(&outline.rasterize(&grid), &grid, 0.5);
+        let is_lit = |x: f32, y: f32| pixels.iter().any(|p| p.distance(Vec2::new(x, y)) < 0.01);
+
+        // Below the bottom edge, which runs along +X from the origin.
+        assert!(is_lit(2.25, -1.05));
+        assert!(!is_lit(2.75, -1.05));
+        assert!(is_lit(7.15, -0.45));
+        assert!(!is_lit(7.85, -0.45));
+        // Inside the footprint, between the lines' columns.
+        assert!(is_lit(2.75, 0.05));
+        assert!(is_lit(5.05, 5.05));
+    }
+
+    #[test]
+    fn rejects_a_chamfer_wider_than_the_margin() {
+        let config = |chamfer_um| SlicerConfig {
+            raft: Some(Raft { chamfer_um, ..Raft::default() }),
+            ..SlicerConfig::default()
+        };
+        assert!(validate(&config(2000.0)).is_ok());
+        assert!(matches!(validate(&config(2500.0)), Err(SliceError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,245 @@
+//! Resin profiles: material properties and the shrinkage they need
+//! compensated.
+
+use crate::{LayerInfo, SliceError, Triangle};
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Exposure settings suggested for a resin. They are recorded in the
+/// manifest for the printer; slicing does not depend on them.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Exposure {
+    pub bottom_layers: u32,
+    pub bottom_exposure_s: f32,
+    pub exposure_s: f32,
+}
+
+impl Default for Exposure {
+    fn default() -> Self {
+        Self {
+            bottom_layers: 5,
+            bottom_exposure_s: 30.0,
+            exposure_s: 2.5,
+        }
+    }
+}
+
+/// A named resin and how it behaves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResinProfile {
+    pub name: String,
+    /// Cured density in grams per cubic centimeter.
+    pub density_g_cm3: f32,
+    /// Price of one liter, in whatever currency the user works in.
+    pub cost_per_liter: f32,
+    /// How much a part shrinks on curing along X, Y and Z, in percent of
+    /// its size. The mesh is scaled up by the inverse before slicing.
+    pub shrinkage_pct: [f32; 3],
+    pub exposure: Exposure,
+}
+
+impl Default for ResinProfile {
+    fn default() -> Self {
+        Self {
+            name: "Generic".to_string(),
+            density_g_cm3: 1.1,
+            cost_per_liter: 30.0,
+            shrinkage_pct: [0.0; 3],
+            exposure: Exposure::default(),
+        }
+    }
+}
+
+impl ResinProfile {
+    /// Factors the mesh is scaled by along X, Y and Z so that it cures to
+    /// the modeled size.
+    pub fn scale(&self) -> Vec3 {
+        Vec3::from_array(self.shrinkage_pct.map(|pct| 100.0 / (100.0 - pct)))
+    }
+}
+
+/// The list of known resin profiles, kept as JSON.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct ResinStore {
+    pub profiles: Vec<ResinProfile>,
+}
+
+impl ResinStore {
+    /// Where the user's profiles live: `rs-licer/resins.json` under
+    /// `$XDG_CONFIG_HOME`, or under `~/.config` if that is not set.
+    pub fn default_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_home.join("rs-licer").join("resins.json"))
+    }
+
+    /// The profiles available without a store file.
+    pub fn builtin() -> Self {
+        ResinStore { profiles: vec![ResinProfile::default()] }
+    }
+
+    /// Reads the profiles in `path` on top of the built-in ones. A missing
+    /// file leaves just the built-in profiles.
+    pub fn load(path: &Path) -> Result<Self, SliceError> {
+        let mut store = Self::builtin();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(SliceError::io(format!("Could not read {}", path.display()))(e)),
+        };
+        let user: ResinStore = serde_json::from_str(&text)
+            .map_err(|e| SliceError::io(format!("Could not parse {}", path.display()))(e.into()))?;
+        for profile in user.profiles {
+            store.insert(profile);
+        }
+        Ok(store)
+    }
+
+    /// Loads the store at [`ResinStore::default_path`], falling back to the
+    /// built-in profiles if it cannot be read.
+    pub fn load_default() -> Self {
+        match Self::default_path().map(|path| Self::load(&path)) {
+            Some(Ok(store)) => store,
+            Some(Err(e)) => {
+                log::warn!("{}", e);
+                Self::builtin()
+            }
+            None => Self::builtin(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SliceError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(SliceError::io("Could not create resin store directory"))?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| SliceError::io("Could not write resin store")(e.into()))?;
+        fs::write(path, json).map_err(SliceError::io("Could not write resin store"))
+    }
+
+    /// Looks a profile up by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&ResinProfile> {
+        self.profiles.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Adds a profile, replacing any with the same name.
+    pub fn insert(&mut self, profile: ResinProfile) {
+        match self.profiles.iter_mut().find(|p| p.name.eq_ignore_ascii_case(&profile.name)) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+}
+
+/// Resin needed for a print, estimated from the layers written.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub(crate) struct ResinUsage {
+    pub(crate) volume_ml: f32,
+    pub(crate) mass_g: f32,
+    pub(crate) cost: f32,
+}
+
+impl ResinUsage {
+    pub(crate) fn estimate(resin: &ResinProfile, layers: &[LayerInfo]) -> Self {
+        let volume_mm3: f32 = layers.iter().map(|l| l.area_mm2 * l.thickness_mm).sum();
+        let volume_ml = volume_mm3 / 1000.0;
+        Self {
+            volume_ml,
+            mass_g: volume_ml * resin.density_g_cm3,
+            cost: volume_ml / 1000.0 * resin.cost_per_liter,
+        }
+    }
+}
+
+pub(crate) fn validate(resin: &ResinProfile) -> Result<(), SliceError> {
+    if resin.shrinkage_pct.iter().any(|pct| !pct.is_finite() || *pct <= -100.0 || *pct >= 100.0) {
+        return Err(SliceError::InvalidConfig(format!(
+            "shrinkage of resin '{}' must be between -100% and 100%",
+            resin.name
+        )));
+    }
+    Ok(())
+}
+
+/// Scales the mesh up to undo the resin's shrinkage, about the center of
+/// its footprint and its lowest point so it stays where it was placed.
+/// Returns that origin and the scale.
+pub(crate) fn compensate_shrinkage(triangles: &mut [Triangle], resin: &ResinProfile) -> (Vec3, Vec3) {
+    let scale = resin.scale();
+    if scale == Vec3::ONE {
+        return (Vec3::ZERO, scale);
+    }
+    let (mut min, mut max) = (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN));
+    for tri in triangles.iter() {
+        min = min.min(tri.v0).min(tri.v1).min(tri.v2);
+        max = max.max(tri.v0).max(tri.v1).max(tri.v2);
+    }
+    let origin = Vec3::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0, min.z);
+    log::info!(
+        "Scaling by {:.3}% x {:.3}% x {:.3}% for resin '{}'",
+        scale.x * 100.0,
+        scale.y * 100.0,
+        scale.z * 100.0,
+        resin.name
+    );
+    for tri in triangles.iter_mut() {
+        for v in [&mut tri.v0, &mut tri.v1, &mut tri.v2] {
+            *v = origin + (*v - origin) * scale;
+        }
+    }
+    (origin, scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shrinking(shrinkage_pct: [f32; 3]) -> ResinProfile {
+        ResinProfile { shrinkage_pct, ..ResinProfile::default() }
+    }
+
+    #[test]
+    fn scales_each_axis_by_its_own_shrinkage() {
+        let scale = shrinking([2.0, 2.0, 5.0]).scale();
+        assert!((scale - Vec3::new(100.0 / 98.0, 100.0 / 98.0, 100.0 / 95.0)).abs().max_element() < 1e-6);
+        assert_eq!(ResinProfile::default().scale(), Vec3::ONE);
+    }
+
+    #[test]
+    fn compensates_about_the_footprint_center_and_base() {
+        let tri = |v0, v1, v2| Triangle { v0, v1, v2, node_index: 0, part: 0 };
+        let mut triangles = vec![
+            tri(Vec3::new(10.0, 20.0, 1.0), Vec3::new(30.0, 20.0, 1.0), Vec3::new(30.0, 40.0, 1.0)),
+            tri(Vec3::new(10.0, 20.0, 1.0), Vec3::new(30.0, 40.0, 1.0), Vec3::new(20.0, 30.0, 11.0)),
+        ];
+        let resin = shrinking([20.0, 20.0, 50.0]);
+        let (origin, scale) = compensate_shrinkage(&mut triangles, &resin);
+
+        assert_eq!(origin, Vec3::new(20.0, 30.0, 1.0));
+        assert_eq!(scale, Vec3::new(1.25, 1.25, 2.0));
+        assert_eq!(triangles[0].v0, Vec3::new(7.5, 17.5, 1.0));
+        assert_eq!(triangles[0].v2, Vec3::new(32.5, 42.5, 1.0));
+        assert_eq!(triangles[1].v2, Vec3::new(20.0, 30.0, 21.0));
+    }
+
+    #[test]
+    fn rejects_shrinkage_of_a_whole_part() {
+        assert!(validate(&shrinking([0.0, 100.0, 0.0])).is_err());
+        assert!(validate(&shrinking([0.0, 0.0, f32::NAN])).is_err());
+        assert!(validate(&shrinking([-5.0, 0.0, 99.0])).is_ok());
+    }
+
+    #[test]
+    fn insert_replaces_profiles_by_name() {
+        let mut store = ResinStore::builtin();
+        store.insert(ResinProfile { name: "generic".to_string(), density_g_cm3: 1.2, ..ResinProfile::default() });
+        assert_eq!(store.profiles.len(), 1);
+        assert_eq!(store.get("GENERIC").unwrap().density_g_cm3, 1.2);
+    }
+}
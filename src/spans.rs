@@ -0,0 +1,131 @@
+//! Compact storage for the per-pixel Z spans produced by raytracing.
+//!
+//! Spans are kept in a single contiguous buffer indexed by a per-pixel offset
+//! table (a CSR layout), so empty pixels cost four bytes and no pixel needs
+//! its own heap allocation.
+
+/// A solid interval along Z, as `(enter, exit)` in millimeters.
+pub type Span = (f32, f32);
+
+/// The spans of one row of pixels, built left to right.
+#[derive(Debug, Clone, Default)]
+pub struct SpanRow {
+    ends: Vec<u32>,
+    spans: Vec<Span>,
+}
+
+impl SpanRow {
+    pub fn with_capacity(width: u32) -> Self {
+        Self {
+            ends: Vec::with_capacity(width as usize),
+            spans: Vec::new(),
+        }
+    }
+
+    /// Appends the spans of the next pixel in the row.
+    pub fn push_pixel(&mut self, spans: &[Span]) {
+        self.spans.extend_from_slice(spans);
+        self.ends.push(self.spans.len() as u32);
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    /// Number of spans in the row.
+    pub fn span_count(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+}
+
+/// Per-pixel spans for a `width` x `height` grid in CSR layout.
+#[derive(Debug, Clone)]
+pub struct SpanGrid {
+    width: u32,
+    height: u32,
+    /// `offsets[i]..offsets[i + 1]` indexes the spans of pixel `i`.
+    offsets: Vec<u32>,
+    spans: Vec<Span>,
+}
+
+/// Returned when a grid holds more spans than a `u32` offset can address.
+#[derive(Debug, Clone, Copy)]
+pub struct SpanOverflow {
+    /// Spans the grid would have held with the row that overflowed it.
+    pub spans: usize,
+}
+
+impl SpanGrid {
+    /// Most spans one grid can hold.
+    pub const MAX_SPANS: usize = u32::MAX as usize;
+
+    /// An empty grid `width` pixels wide, with offsets reserved for `rows`
+    /// rows.
+    pub fn with_capacity(width: u32, rows: u32) -> Self {
+        let mut offsets = Vec::with_capacity(rows as usize * width as usize + 1);
+        offsets.push(0u32);
+        Self {
+            width,
+            height: 0,
+            offsets,
+            spans: Vec::new(),
+        }
+    }
+
+    /// Appends `row`, which must hold exactly `width` pixels, below the rows
+    /// already in the grid.
+    pub fn push_row(&mut self, row: &SpanRow) -> Result<(), SpanOverflow> {
+        assert_eq!(row.len(), self.width as usize, "span row has the wrong width");
+        let base = self.spans.len();
+        if base + row.spans.len() > Self::MAX_SPANS {
+            return Err(SpanOverflow { spans: base + row.spans.len() });
+        }
+        self.offsets.extend(row.ends.iter().map(|end| base as u32 + end));
+        self.spans.extend_from_slice(&row.spans);
+        self.height += 1;
+        Ok(())
+    }
+
+    /// Makes room for at least `additional` more spans without reserving
+    /// any beyond that.
+    pub fn reserve_spans(&mut self, additional: usize) {
+        self.spans.reserve_exact(additional);
+    }
+
+    /// Releases spare capacity once every row has been pushed.
+    pub fn shrink_to_fit(&mut self) {
+        self.offsets.shrink_to_fit();
+        self.spans.shrink_to_fit();
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Spans of the pixel at `(x, y)`, sorted by Z.
+    pub fn pixel(&self, x: u32, y: u32) -> &[Span] {
+        let idx = (y * self.width + x) as usize;
+        let start = self.offsets[idx] as usize;
+        let end = self.offsets[idx + 1] as usize;
+        &self.spans[start..end]
+    }
+
+    /// Total number of spans stored.
+    pub fn span_count(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Bytes of heap memory owned by the grid.
+    pub fn heap_bytes(&self) -> usize {
+        self.offsets.capacity() * std::mem::size_of::<u32>()
+            + self.spans.capacity() * std::mem::size_of::<Span>()
+    }
+}
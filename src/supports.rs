@@ -0,0 +1,595 @@
+//! Support structures generated under the overhangs and low points of the
+//! mesh and merged into it before slicing.
+//!
+//! Supports are built from tubes, each a closed solid of its own numbered
+//! part, so the raycast engine can union them with the model and with each
+//! other.
+
+use crate::{FillRule, SliceEngine, SliceError, SlicerConfig, Triangle};
+use glam::{Vec2, Vec3};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+use std::fmt;
+
+/// Shape of the generated supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupportStyle {
+    /// A straight pillar from the plate, or the model below, to each contact.
+    #[default]
+    Pillar,
+    /// Nearby contacts branch off a shared trunk, using less resin.
+    Tree,
+}
+
+impl SupportStyle {
+    pub const ALL: [SupportStyle; 2] = [SupportStyle::Pillar, SupportStyle::Tree];
+}
+
+impl fmt::Display for SupportStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SupportStyle::Pillar => write!(f, "Pillar"),
+            SupportStyle::Tree => write!(f, "Tree"),
+        }
+    }
+}
+
+/// Automatic supports. Sizes are in micrometers.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Supports {
+    pub style: SupportStyle,
+    /// Downward surfaces closer than this to horizontal are supported, in
+    /// degrees.
+    pub overhang_angle_deg: f32,
+    /// Distance between contacts on an overhang.
+    pub spacing_um: f32,
+    pub tip_diameter_um: f32,
+    /// Length of the cone narrowing from the pillar to the tip.
+    pub tip_length_um: f32,
+    pub pillar_diameter_um: f32,
+    /// Diameter of the foot widening onto the build plate.
+    pub base_diameter_um: f32,
+    pub base_height_um: f32,
+    /// How far the model is raised off the plate to make room.
+    pub elevation_um: f32,
+}
+
+impl Default for Supports {
+    fn default() -> Self {
+        Self {
+            style: SupportStyle::Pillar,
+            overhang_angle_deg: 45.0,
+            spacing_um: 2500.0,
+            tip_diameter_um: 400.0,
+            tip_length_um: 1500.0,
+            pillar_diameter_um: 1000.0,
+            base_diameter_um: 3000.0,
+            base_height_um: 500.0,
+            elevation_um: 5000.0,
+        }
+    }
+}
+
+pub(crate) fn validate(config: &SlicerConfig) -> Result<(), SliceError> {
+    let Some(supports) = config.supports else {
+        return Ok(());
+    };
+    if config.engine == SliceEngine::Scanline && config.fill_rule == FillRule::EvenOdd {
+        return Err(SliceError::InvalidConfig(
+            "supports need the raycast engine or the nonzero fill rule".to_string(),
+        ));
+    }
+    if !(supports.overhang_angle_deg > 0.0 && supports.overhang_angle_deg < 90.0) {
+        return Err(SliceError::InvalidConfig(
+            "support overhang angle must be between 0 and 90 degrees".to_string(),
+        ));
+    }
+    let sizes = [
+        supports.spacing_um,
+        supports.tip_diameter_um,
+        supports.tip_length_um,
+        supports.pillar_diameter_um,
+        supports.base_diameter_um,
+        supports.base_height_um,
+        supports.elevation_um,
+    ];
+    if !sizes.iter().all(|size| size.is_finite() && *size > 0.0) {
+        return Err(SliceError::InvalidConfig("support sizes must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Sides of every tube.
+const SEGMENTS: usize = 8;
+
+/// A point of the mesh to hold up, and the Z of the model surface under it
+/// or `None` if it is over the plate.
+#[derive(Debug, Clone, Copy)]
+struct Contact {
+    point: Vec3,
+    below: Option<f32>,
+}
+
+/// Raises the model by the elevation and adds supports under it. Returns
+/// how far the model was raised.
+pub(crate) fn generate(supports: &Supports, triangles: &mut Vec<Triangle>) -> f32 {
+    let plate_z = triangles
+        .iter()
+        .flat_map(|tri| [tri.v0.z, tri.v1.z, tri.v2.z])
+        .fold(f32::MAX, f32::min);
+    let lift_mm = supports.elevation_um / 1000.0;
+    for tri in triangles.iter_mut() {
+        for v in [&mut tri.v0, &mut tri.v1, &mut tri.v2] {
+            v.z += lift_mm;
+        }
+    }
+
+    let builder = Builder::new(supports, plate_z);
+    let columns = Columns::new(triangles, builder.spacing);
+    let contacts = find_contacts(supports, triangles, &columns);
+    let mut parts = Vec::new();
+    match supports.style {
+        SupportStyle::Pillar => {
+            for contact in &contacts {
+                parts.push(builder.pillar(contact));
+            }
+        }
+        SupportStyle::Tree => {
+            for cluster in builder.clusters(&contacts) {
+                match builder.tree(&cluster, &columns, triangles) {
+                    Some(tree) => parts.extend(tree),
+                    None => parts.extend(cluster.iter().map(|contact| builder.pillar(contact))),
+                }
+            }
+        }
+    }
+
+    let model_triangles = triangles.len();
+    for (part, tube) in parts.iter().enumerate() {
+        triangles.extend(tube.iter().map(|tri| Triangle { part: part as u32 + 1, ..*tri }));
+    }
+    log::info!(
+        "Added {} supports from {} contacts in {} triangles",
+        parts.len(),
+        contacts.len(),
+        triangles.len() - model_triangles
+    );
+    lift_mm
+}
+
+/// Points to support: where a regular lattice crosses an overhang, and the
+/// lowest vertices of the mesh, which a lattice can miss.
+fn find_contacts(supports: &Supports, triangles: &[Triangle], columns: &Columns) -> Vec<Contact> {
+    let min_down = supports.overhang_angle_deg.to_radians().cos();
+    let is_overhang = |tri: &Triangle| {
+        let normal = (tri.v1 - tri.v0).cross(tri.v2 - tri.v0).normalize_or_zero();
+        -normal.z > min_down
+    };
+    let mut candidates = Vec::new();
+
+    for (x, y) in columns.lattice() {
+        let hits = columns.hits(triangles, x, y);
+        for (k, &(z, index)) in hits.iter().enumerate() {
+            if is_overhang(&triangles[index]) {
+                let below = k.checked_sub(1).map(|k| hits[k].0);
+                candidates.push(Contact { point: Vec3::new(x, y, z), below });
+            }
+        }
+    }
+
+    // A vertex of a downward face no lower than any vertex it shares a
+    // triangle with is the bottom of something.
+    let mut lowest: HashMap<[u32; 3], (bool, bool)> = HashMap::new();
+    for tri in triangles {
+        let faces_down = (tri.v1 - tri.v0).cross(tri.v2 - tri.v0).z < 0.0;
+        for (v, others) in [(tri.v0, [tri.v1, tri.v2]), (tri.v1, [tri.v0, tri.v2]), (tri.v2, [tri.v0, tri.v1])] {
+            let key = v.to_array().map(f32::to_bits);
+            let entry = lowest.entry(key).or_insert((true, false));
+            entry.0 &= others.iter().all(|other| other.z >= v.z);
+            entry.1 |= faces_down;
+        }
+    }
+    let mut minima: Vec<Vec3> = lowest
+        .into_iter()
+        .filter(|(_, (low, down))| *low && *down)
+        .map(|(key, _)| Vec3::from_array(key.map(f32::from_bits)))
+        .collect();
+    minima.sort_by(|a, b| a.z.total_cmp(&b.z).then(a.x.total_cmp(&b.x)).then(a.y.total_cmp(&b.y)));
+    for point in minima {
+        let hits = columns.hits(triangles, point.x, point.y);
+        let below = hits.iter().rev().find(|&&(z, _)| z < point.z - 1e-4);
+        // Skip points inside the model, where the surface below faces down.
+        match below {
+            Some(&(_, index)) if !triangles[index].faces_up() => {}
+            _ => candidates.push(Contact { point, below: below.map(|&(z, _)| z) }),
+        }
+    }
+
+    // Drop contacts crowding one already kept, or too close to the surface
+    // under them for a tip to fit.
+    let spacing = supports.spacing_um / 1000.0;
+    let tip_length = supports.tip_length_um / 1000.0;
+    let mut kept: Vec<Contact> = Vec::new();
+    for contact in candidates {
+        if contact.below.is_some_and(|below| contact.point.z - below < tip_length) {
+            continue;
+        }
+        let crowded = kept.iter().any(|other| {
+            other.point.truncate().distance(contact.point.truncate()) < spacing / 2.0
+                && (other.point.z - contact.point.z).abs() < spacing
+        });
+        if !crowded {
+            kept.push(contact);
+        }
+    }
+    kept
+}
+
+/// Triangles binned by the lattice cells their XY bounds cover, to find
+/// those above or below a point quickly.
+pub(crate) struct Columns {
+    origin: Vec2,
+    cell: f32,
+    size: (usize, usize),
+    bins: Vec<Vec<usize>>,
+}
+
+impl Columns {
+    pub(crate) fn new(triangles: &[Triangle], cell: f32) -> Self {
+        let (mut min, mut max) = (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN));
+        for tri in triangles {
+            for v in [tri.v0, tri.v1, tri.v2] {
+                min = min.min(v.truncate());
+                max = max.max(v.truncate());
+            }
+        }
+        let size = (((max.x - min.x) / cell) as usize + 1, ((max.y - min.y) / cell) as usize + 1);
+        let mut columns = Self { origin: min, cell, size, bins: vec![Vec::new(); size.0 * size.1] };
+        for (index, tri) in triangles.iter().enumerate() {
+            let low = tri.v0.truncate().min(tri.v1.truncate()).min(tri.v2.truncate());
+            let high = tri.v0.truncate().max(tri.v1.truncate()).max(tri.v2.truncate());
+            let (x0, y0) = columns.cell_of(low);
+            let (x1, y1) = columns.cell_of(high);
+            for y in y0..=y1 {
+                for x in x0..=x1 {
+                    columns.bins[y * size.0 + x].push(index);
+                }
+            }
+        }
+        columns
+    }
+
+    fn cell_of(&self, p: Vec2) -> (usize, usize) {
+        let cell = ((p - self.origin) / self.cell).max(Vec2::ZERO);
+        ((cell.x as usize).min(self.size.0 - 1), (cell.y as usize).min(self.size.1 - 1))
+    }
+
+    /// Number of lattice cells along X and Y.
+    pub(crate) fn size(&self) -> (usize, usize) {
+        self.size
+    }
+
+    /// Centers of the lattice cells, row by row.
+    pub(crate) fn lattice(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        (0..self.size.1).flat_map(move |y| {
+            (0..self.size.0).map(move |x| {
+                (self.origin.x + (x as f32 + 0.5) * self.cell, self.origin.y + (y as f32 + 0.5) * self.cell)
+            })
+        })
+    }
+
+    /// Where a vertical line through `(x, y)` crosses the mesh, as Z and
+    /// triangle index from the bottom up. A crossing on an edge shared by
+    /// triangles facing the same way counts once.
+    pub(crate) fn hits(&self, triangles: &[Triangle], x: f32, y: f32) -> Vec<(f32, usize)> {
+        let p = Vec2::new(x, y);
+        let (cx, cy) = self.cell_of(p);
+        let mut hits: Vec<(f32, usize)> = self.bins[cy * self.size.0 + cx]
+            .iter()
+            .filter_map(|&index| z_at(&triangles[index], p).map(|z| (z, index)))
+            .collect();
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+        hits.dedup_by(|b, a| b.0 - a.0 < 1e-5 && triangles[b.1].faces_up() == triangles[a.1].faces_up());
+        hits
+    }
+
+    /// Whether a vertical segment through `(x, y)` from `z0` to `z1` passes
+    /// through the mesh.
+    fn blocked(&self, triangles: &[Triangle], x: f32, y: f32, z0: f32, z1: f32) -> bool {
+        self.hits(triangles, x, y).iter().any(|&(z, _)| z > z0 && z < z1)
+    }
+
+    /// Whether a straight segment from `a` to `b` passes through the mesh.
+    /// It is followed in steps of at most a quarter cell, each checked for a
+    /// surface crossed on the way up or down and for ending inside the mesh,
+    /// which catches walls too steep to cross within a step.
+    fn segment_blocked(&self, triangles: &[Triangle], a: Vec3, b: Vec3) -> bool {
+        let steps = ((b - a).truncate().length() / (self.cell / 4.0)).ceil().max(1.0) as usize;
+        (0..steps).any(|i| {
+            let (p, q) = (a.lerp(b, i as f32 / steps as f32), a.lerp(b, (i + 1) as f32 / steps as f32));
+            let inside = self.hits(triangles, q.x, q.y).iter().filter(|&&(z, _)| z < q.z).count() % 2 == 1;
+            inside || self.blocked(triangles, p.x, p.y, p.z.min(q.z), p.z.max(q.z))
+        })
+    }
+}
+
+/// Height of a triangle above a point, if the point lies under it.
+fn z_at(tri: &Triangle, p: Vec2) -> Option<f32> {
+    let (a, b, c) = (tri.v0.truncate(), tri.v1.truncate(), tri.v2.truncate());
+    let area = (b - a).perp_dot(c - a);
+    if area.abs() < 1e-12 {
+        return None;
+    }
+    let u = (c - b).perp_dot(p - b) / area;
+    let v = (a - c).perp_dot(p - c) / area;
+    let w = 1.0 - u - v;
+    (u >= 0.0 && v >= 0.0 && w >= 0.0).then_some(u * tri.v0.z + v * tri.v1.z + w * tri.v2.z)
+}
+
+/// Support sizes in millimeters, and the tubes built from them.
+struct Builder {
+    plate_z: f32,
+    spacing: f32,
+    tip_radius: f32,
+    /// How far tips reach into the model, enough to bury their whole rim
+    /// in the steepest surface supported.
+    tip_depth: f32,
+    tip_length: f32,
+    pillar_radius: f32,
+    base_radius: f32,
+    base_height: f32,
+}
+
+impl Builder {
+    fn new(supports: &Supports, plate_z: f32) -> Self {
+        Self {
+            plate_z,
+            spacing: supports.spacing_um / 1000.0,
+            tip_radius: supports.tip_diameter_um / 2000.0,
+            tip_depth: supports.tip_diameter_um / 2000.0 * (1.0 + supports.overhang_angle_deg.to_radians().tan()),
+            tip_length: supports.tip_length_um / 1000.0,
+            pillar_radius: supports.pillar_diameter_um / 2000.0,
+            base_radius: supports.base_diameter_um / 2000.0,
+            base_height: supports.base_height_um / 1000.0,
+        }
+    }
+
+    /// A pillar standing on the plate, or on a tip of its own on the model
+    /// below, up to a contact.
+    fn pillar(&self, contact: &Contact) -> Vec<Triangle> {
+        let Vec3 { x, y, z: top } = contact.point;
+        let at = |radius: f32, z: f32| (Vec3::new(x, y, z), radius);
+        let (foot, foot_height) = match contact.below {
+            Some(below) => (at(self.tip_radius, below - self.tip_depth), self.tip_length),
+            None => (at(self.base_radius, self.plate_z), self.base_height),
+        };
+        let bottom = foot.0.z.max(contact.below.unwrap_or(self.plate_z));
+        let tip = at(self.tip_radius, top + self.tip_depth);
+        if top - bottom > foot_height + self.tip_length {
+            tube(&[
+                foot,
+                at(self.pillar_radius, bottom + foot_height),
+                at(self.pillar_radius, top - self.tip_length),
+                tip,
+            ])
+        } else {
+            // Too short for a shaft: narrow straight from the foot to the tip.
+            tube(&[foot, tip])
+        }
+    }
+
+    /// Groups contacts over the plate that can share a trunk. Contacts over
+    /// the model stay alone.
+    fn clusters(&self, contacts: &[Contact]) -> Vec<Vec<Contact>> {
+        let reach = 2.0 * self.spacing;
+        let mut clusters: Vec<Vec<Contact>> = Vec::new();
+        for contact in contacts {
+            let joined = contact.below.is_none()
+                && clusters.iter_mut().any(|cluster| {
+                    let seed = cluster[0];
+                    let near = seed.below.is_none()
+                        && seed.point.truncate().distance(contact.point.truncate()) <= reach;
+                    if near {
+                        cluster.push(*contact);
+                    }
+                    near
+                });
+            if !joined {
+                clusters.push(vec![*contact]);
+            }
+        }
+        clusters
+    }
+
+    /// A trunk under the middle of a cluster with a branch rising at 45°
+    /// towards each contact, and pillars to contacts whose branch would pass
+    /// through the model. `None` if fewer than two contacts can share the
+    /// trunk or there is no room for it.
+    fn tree(&self, cluster: &[Contact], columns: &Columns, triangles: &[Triangle]) -> Option<Vec<Vec<Triangle>>> {
+        let mut members = cluster.to_vec();
+        let mut parts = Vec::new();
+        loop {
+            if members.len() < 2 {
+                return None;
+            }
+            let middle = members.iter().map(|contact| contact.point.truncate()).sum::<Vec2>() / members.len() as f32;
+            let branch_start = |contact: &Contact| contact.point - Vec3::Z * self.tip_length;
+            let trunk_top = members
+                .iter()
+                .map(|contact| branch_start(contact).z - branch_start(contact).truncate().distance(middle))
+                .fold(f32::MAX, f32::min);
+            let highest = members.iter().map(|contact| contact.point.z).fold(f32::MIN, f32::max);
+            if trunk_top - self.plate_z < self.base_height + self.pillar_radius
+                || columns.blocked(triangles, middle.x, middle.y, self.plate_z, highest)
+            {
+                return None;
+            }
+            // Rise at 45° to below each contact, then straight up to it.
+            let elbow = |contact: &Contact| {
+                let reach = contact.point.truncate().distance(middle);
+                contact.point.truncate().extend(trunk_top + reach)
+            };
+
+            let (clear, blocked): (Vec<Contact>, Vec<Contact>) = members.iter().partition(|contact| {
+                !columns.segment_blocked(triangles, middle.extend(trunk_top), elbow(contact))
+                    && !columns.segment_blocked(triangles, elbow(contact), branch_start(contact))
+            });
+            if !blocked.is_empty() {
+                // Build the tree again around the contacts it can reach.
+                parts.extend(blocked.iter().map(|contact| self.pillar(contact)));
+                members = clear;
+                continue;
+            }
+
+            let at = |radius: f32, z: f32| (middle.extend(z), radius);
+            parts.push(tube(&[
+                at(self.base_radius, self.plate_z),
+                at(self.pillar_radius, self.plate_z + self.base_height),
+                // Past the top so it fills the fork between the branches.
+                at(self.pillar_radius, trunk_top + 2.0 * self.pillar_radius),
+            ]));
+            for contact in &members {
+                let elbow = elbow(contact);
+                if contact.point.truncate().distance(middle) > self.pillar_radius {
+                    parts.push(tube(&[(middle.extend(trunk_top), self.pillar_radius), (elbow, self.pillar_radius)]));
+                }
+                parts.push(tube(&[
+                    (elbow - Vec3::Z * self.pillar_radius, self.pillar_radius),
+                    (branch_start(contact), self.pillar_radius),
+                    (contact.point + Vec3::Z * self.tip_depth, self.tip_radius),
+                ]));
+            }
+            return Some(parts);
+        }
+    }
+}
+
+/// A closed tube through ring centers on one straight line, each ring
+/// square to the line, with outward-facing triangles.
+fn tube(rings: &[(Vec3, f32)]) -> Vec<Triangle> {
+    let (first, last) = (rings[0].0, rings[rings.len() - 1].0);
+    let axis = (last - first).normalize();
+    let u = axis.any_orthonormal_vector();
+    let v = axis.cross(u);
+    let ring = |(center, radius): (Vec3, f32)| -> Vec<Vec3> {
+        (0..SEGMENTS)
+            .map(|i| {
+                let angle = i as f32 * TAU / SEGMENTS as f32;
+                center + (u * angle.cos() + v * angle.sin()) * radius
+            })
+            .collect()
+    };
+    let triangle = |v0, v1, v2| Triangle { v0, v1, v2, node_index: 0, part: 0 };
+
+    let points: Vec<Vec<Vec3>> = rings.iter().copied().map(ring).collect();
+    let mut triangles = Vec::with_capacity(2 * SEGMENTS * rings.len());
+    for i in 0..SEGMENTS {
+        let j = (i + 1) % SEGMENTS;
+        triangles.push(triangle(first, points[0][j], points[0][i]));
+        for pair in points.windows(2) {
+            let (lower, upper) = (&pair[0], &pair[1]);
+            triangles.push(triangle(lower[i], lower[j], upper[j]));
+            triangles.push(triangle(lower[i], upper[j], upper[i]));
+        }
+        triangles.push(triangle(last, points[points.len() - 1][i], points[points.len() - 1][j]));
+    }
+    triangles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::cuboid;
+
+    fn bounds(part: &[Triangle]) -> (Vec3, Vec3) {
+        part.iter()
+            .flat_map(|tri| [tri.v0, tri.v1, tri.v2])
+            .fold((Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)), |(min, max), v| (min.min(v), max.max(v)))
+    }
+
+    fn over_plate(x: f32, y: f32, z: f32) -> Contact {
+        Contact { point: Vec3::new(x, y, z), below: None }
+    }
+
+    #[test]
+    fn pillars_reach_from_plate_or_model_into_the_contact() {
+        let builder = Builder::new(&Supports::default(), 0.0);
+        let (min, max) = bounds(&builder.pillar(&over_plate(3.0, 4.0, 10.0)));
+        assert!((min.z - 0.0).abs() < 1e-5 && (max.z - (10.0 + builder.tip_depth)).abs() < 1e-5);
+        assert!((min.truncate() - Vec2::new(3.0 - builder.base_radius, 4.0 - builder.base_radius)).length() < 0.1);
+
+        // Standing on the model, the foot is a tip buried in the surface.
+        let on_model = Contact { point: Vec3::new(3.0, 4.0, 10.0), below: Some(6.0) };
+        let (min, max) = bounds(&builder.pillar(&on_model));
+        assert!((min.z - (6.0 - builder.tip_depth)).abs() < 1e-5 && max.x - min.x <= 2.0 * builder.pillar_radius);
+    }
+
+    #[test]
+    fn supports_every_overhang_contact_down_to_the_plate() {
+        // A 10 x 10 mm slab, raised 5 mm onto supports.
+        let mut triangles = cuboid(Vec3::ZERO, Vec3::new(10.0, 10.0, 2.0));
+        let model = triangles.len();
+        let supports = Supports::default();
+        let tip_depth = Builder::new(&supports, 0.0).tip_depth;
+        assert_eq!(generate(&supports, &mut triangles), 5.0);
+
+        let parts: Vec<Vec<Triangle>> =
+            triangles[model..].chunk_by(|a, b| a.part == b.part).map(<[Triangle]>::to_vec).collect();
+        // A 4 x 4 lattice of contacts 2.5 mm apart under the slab, and its
+        // four lowest corners.
+        assert_eq!(parts.len(), 20);
+        for part in &parts {
+            let (min, max) = bounds(part);
+            assert_eq!(min.z, 0.0);
+            assert!((max.z - (5.0 + tip_depth)).abs() < 1e-4, "tip at Z {}", max.z);
+            let center = (min + max).truncate() / 2.0;
+            assert!(center.min_element() >= 0.0 && center.max_element() <= 10.0, "pillar at {}", center);
+        }
+    }
+
+    #[test]
+    fn trees_share_a_trunk() {
+        let builder = Builder::new(&Supports::default(), 0.0);
+        let cluster = [over_plate(0.0, 0.0, 10.0), over_plate(2.0, 0.0, 10.0), over_plate(4.0, 0.0, 10.0)];
+        // The model is well away from the tree.
+        let model = cuboid(Vec3::new(20.0, 0.0, 0.0), Vec3::new(21.0, 1.0, 1.0));
+        let columns = Columns::new(&model, 2.5);
+        let parts = builder.tree(&cluster, &columns, &model).expect("room for a trunk");
+
+        // One part stands on the plate, under the middle of the contacts.
+        let feet: Vec<_> = parts.iter().map(|part| bounds(part)).filter(|(min, _)| min.z == 0.0).collect();
+        assert_eq!(feet.len(), 1);
+        assert!(((feet[0].0 + feet[0].1).x / 2.0 - 2.0).abs() < 1e-4);
+        // Each contact is reached by a branch ending in a tip.
+        for contact in &cluster {
+            let top = contact.point.z + builder.tip_depth;
+            assert!(parts.iter().any(|part| {
+                let (min, max) = bounds(part);
+                (max.z - top).abs() < 1e-4 && ((min.x + max.x) / 2.0 - contact.point.x).abs() < 1e-4
+            }));
+        }
+    }
+
+    #[test]
+    fn trees_give_blocked_branches_pillars() {
+        let builder = Builder::new(&Supports::default(), 0.0);
+        let cluster = [over_plate(0.0, 0.0, 10.0), over_plate(2.0, 0.0, 10.0), over_plate(10.0, 0.0, 10.0)];
+        // The trunk stands at X 4 mm with its top at Z 2.5 mm, so the branch
+        // to the contact at X 10 mm passes Z 5.5 mm at X 7 mm.
+        let obstacle = cuboid(Vec3::new(6.5, -1.0, 4.0), Vec3::new(7.5, 1.0, 7.0));
+        let columns = Columns::new(&obstacle, 2.5);
+        assert!(columns.segment_blocked(&obstacle, Vec3::new(4.0, 0.0, 2.5), Vec3::new(10.0, 0.0, 8.5)));
+
+        let parts = builder.tree(&cluster, &columns, &obstacle).expect("room for a trunk");
+        let spans = |part: &Vec<Triangle>| {
+            let (min, max) = bounds(part);
+            (min.x, max.x, min.z)
+        };
+        // A pillar rises from the plate to the blocked contact, and no branch
+        // crosses the obstacle.
+        assert!(parts.iter().map(spans).any(|(x0, x1, z)| z == 0.0 && ((x0 + x1) / 2.0 - 10.0).abs() < 1e-4));
+        assert!(parts.iter().map(spans).all(|(x0, x1, _)| x1 < 6.5 || x0 > 7.5));
+    }
+}
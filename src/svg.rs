@@ -0,0 +1,145 @@
+//! SVG export of layer cross-sections.
+
+use crate::contour::{FillRule, Polygon, Sectioner};
+use crate::layers::Layer;
+use crate::{layer_path, PlateGrid, SliceError, SlicerConfig, Triangle, SECTION_OFFSET_MM};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::path::Path;
+
+/// Whether and how layer contours are exported as SVG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SvgOutput {
+    #[default]
+    Off,
+    /// One SVG per layer, named like the layer's PNG.
+    PerLayer,
+    /// A single `layers.svg` with one group per layer.
+    Combined,
+}
+
+impl SvgOutput {
+    pub const ALL: [SvgOutput; 3] = [SvgOutput::Off, SvgOutput::PerLayer, SvgOutput::Combined];
+}
+
+impl fmt::Display for SvgOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SvgOutput::Off => write!(f, "Off"),
+            SvgOutput::PerLayer => write!(f, "Per Layer"),
+            SvgOutput::Combined => write!(f, "Combined"),
+        }
+    }
+}
+
+/// File name of the combined multi-layer SVG.
+pub(crate) const COMBINED_FILE_NAME: &str = "layers.svg";
+
+/// Builds a standalone SVG document for one layer.
+fn layer_document(polygons: &[Polygon], grid: &PlateGrid, config: &SlicerConfig) -> String {
+    let mut svg = header(grid, config);
+    let _ = writeln!(svg, "  {}", path_element(polygons, grid, config));
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Builds one SVG document holding every layer as a separate group, bottom
+/// layer first. Groups are marked as Inkscape layers so they can be toggled.
+fn combined_document(
+    sections: &[(Layer, Vec<Polygon>)],
+    grid: &PlateGrid,
+    config: &SlicerConfig,
+) -> String {
+    let mut svg = header(grid, config);
+    for (layer, polygons) in sections {
+        let _ = writeln!(
+            svg,
+            concat!(
+                "  <g id=\"layer-{}\" inkscape:groupmode=\"layer\" inkscape:label=\"Z {:.4} mm\" ",
+                "data-z-mm=\"{}\" data-thickness-mm=\"{}\">"
+            ),
+            layer.index, layer.z, layer.z, layer.thickness_mm
+        );
+        let _ = writeln!(svg, "    {}", path_element(polygons, grid, config));
+        svg.push_str("  </g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Opens an SVG whose user units are millimeters over the plate footprint,
+/// laid out like the layer images.
+fn header(grid: &PlateGrid, config: &SlicerConfig) -> String {
+    let (width, height) = config.orientation.size(footprint_mm(grid));
+    format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" ",
+            "xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" ",
+            "width=\"{w}mm\" height=\"{h}mm\" viewBox=\"0 0 {w} {h}\">\n"
+        ),
+        w = width,
+        h = height
+    )
+}
+
+/// One path with a subpath per polygon, so holes cut through their outlines.
+fn path_element(polygons: &[Polygon], grid: &PlateGrid, config: &SlicerConfig) -> String {
+    let fill_rule = match config.fill_rule {
+        FillRule::EvenOdd => "evenodd",
+        FillRule::NonZero => "nonzero",
+    };
+    // Flip Y so the SVG matches the orientation of the PNG layers.
+    let size = footprint_mm(grid);
+    let top = grid.min_bound.y + size.1;
+    let mut d = String::new();
+    for polygon in polygons {
+        for (i, p) in polygon.points.iter().enumerate() {
+            let command = if i == 0 { 'M' } else { 'L' };
+            let (x, y) = config.orientation.map((p.x - grid.min_bound.x, top - p.y), size);
+            let _ = write!(d, "{}{:.4} {:.4} ", command, x, y);
+        }
+        d.push('Z');
+    }
+    format!("<path fill=\"black\" fill-rule=\"{}\" d=\"{}\"/>", fill_rule, d)
+}
+
+/// Width and height of the area covered by the layer images.
+fn footprint_mm(grid: &PlateGrid) -> (f32, f32) {
+    (
+        grid.width_px as f32 * grid.pixel_size_x_mm,
+        grid.height_px as f32 * grid.pixel_size_y_mm,
+    )
+}
+
+/// Cuts every layer through the mesh and writes the configured SVG files.
+pub(crate) fn export(
+    config: &SlicerConfig,
+    triangles: &[Triangle],
+    grid: &PlateGrid,
+    layers: &[Layer],
+) -> Result<(), SliceError> {
+    let sectioner = Sectioner::new(triangles);
+    let section = |layer: &Layer| sectioner.section(layer.z + SECTION_OFFSET_MM);
+
+    match config.svg_output {
+        SvgOutput::Off => Ok(()),
+        SvgOutput::PerLayer => layers.par_iter().try_for_each(|layer| {
+            let svg = layer_document(&section(layer), grid, config);
+            fs::write(layer_path(config, layer, "svg"), svg)
+                .map_err(SliceError::io("Could not write SVG"))
+        }),
+        SvgOutput::Combined => {
+            let sections: Vec<(Layer, Vec<Polygon>)> = layers
+                .par_iter()
+                .map(|layer| (layer.clone(), section(layer)))
+                .collect();
+            let svg = combined_document(&sections, grid, config);
+            let path = Path::new(&config.output_dir).join(COMBINED_FILE_NAME);
+            fs::write(path, svg).map_err(SliceError::io("Could not write SVG"))
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use rs_licer::{ProgressEvent, ProgressSink};
+use std::io::{self, IsTerminal, Write};
+use std::mem::Discriminant;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How much the CLI prints while slicing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Verbosity {
+    fn level_filter(self) -> LevelFilter {
+        match self {
+            Verbosity::Quiet => LevelFilter::Error,
+            Verbosity::Normal => LevelFilter::Info,
+            Verbosity::Verbose => LevelFilter::Debug,
+        }
+    }
+}
+
+static LOGGER: TerminalLogger = TerminalLogger;
+
+/// Installs a logger that writes library diagnostics to stderr.
+pub fn init_logging(verbosity: Verbosity) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(verbosity.level_filter());
+    }
+}
+
+struct TerminalLogger;
+
+impl Log for TerminalLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("rs_licer")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+        // Clear any progress bar drawn on the current line first.
+        if stderr.is_terminal() {
+            let _ = write!(stderr, "\r\x1b[K");
+        }
+        let _ = match record.level() {
+            Level::Error => writeln!(stderr, "Error: {}", record.args()),
+            Level::Warn => writeln!(stderr, "Warning: {}", record.args()),
+            _ => writeln!(stderr, "{}", record.args()),
+        };
+    }
+
+    fn flush(&self) {
+        let _ = io::stderr().flush();
+    }
+}
+
+/// Progress sink that draws a single-line bar with an ETA on stderr.
+pub struct TerminalProgress {
+    enabled: bool,
+    state: Mutex<BarState>,
+}
+
+struct BarState {
+    phase: Option<Discriminant<ProgressEvent>>,
+    phase_start: Instant,
+    last_draw: Option<Instant>,
+}
+
+impl TerminalProgress {
+    /// Creates a bar that only draws when stderr is an interactive terminal.
+    pub fn new(verbosity: Verbosity) -> Self {
+        Self {
+            enabled: verbosity != Verbosity::Quiet && io::stderr().is_terminal(),
+            state: Mutex::new(BarState {
+                phase: None,
+                phase_start: Instant::now(),
+                last_draw: None,
+            }),
+        }
+    }
+}
+
+impl ProgressSink for TerminalProgress {
+    fn report(&self, event: ProgressEvent) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.lock().unwrap();
+
+        let phase = std::mem::discriminant(&event);
+        if state.phase != Some(phase) {
+            state.phase = Some(phase);
+            state.phase_start = Instant::now();
+            state.last_draw = None;
+        }
+
+        let terminal = event.is_terminal();
+        if !terminal && state.last_draw.is_some_and(|t| t.elapsed() < REDRAW_INTERVAL) {
+            return;
+        }
+        state.last_draw = Some(Instant::now());
+
+        let mut stderr = io::stderr().lock();
+        if terminal {
+            let _ = write!(stderr, "\r\x1b[K");
+            let _ = stderr.flush();
+            return;
+        }
+
+        let fraction = event.phase_fraction();
+        let bar = match fraction {
+            Some(fraction) => {
+                let filled = ((fraction * BAR_WIDTH as f32) as usize).min(BAR_WIDTH);
+                format!(
+                    "[{}{}] {:>3}%",
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    (fraction * 100.0) as u32
+                )
+            }
+            None => String::new(),
+        };
+        let eta = match fraction {
+            Some(fraction) if fraction > 0.0 && fraction < 1.0 => {
+                let elapsed = state.phase_start.elapsed().as_secs_f32();
+                let remaining = elapsed / fraction - elapsed;
+                format!(" ETA {}m {}s", (remaining / 60.0) as u32, (remaining % 60.0) as u32)
+            }
+            _ => String::new(),
+        };
+
+        let _ = write!(stderr, "\r\x1b[K{} {}{}", phase_label(&event), bar, eta);
+        let _ = stderr.flush();
+    }
+}
+
+fn phase_label(event: &ProgressEvent) -> &'static str {
+    match event {
+        ProgressEvent::Loading => "Loading",
+        ProgressEvent::Orienting { .. } => "Orienting",
+        ProgressEvent::BuildingBvh { .. } => "Building BVH",
+        ProgressEvent::Raytracing { .. } => "Raytracing",
+        ProgressEvent::Rendering { .. } => "Rendering",
+        ProgressEvent::Writing => "Writing",
+        ProgressEvent::Finishing => "Finishing",
+        ProgressEvent::Finished(_) => "Finished",
+        ProgressEvent::Failed(_) => "Failed",
+    }
+}
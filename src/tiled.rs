@@ -0,0 +1,176 @@
+//! Band-by-band slicing for plates whose spans do not fit in memory.
+//!
+//! The plate is traced a band of rows at a time. Each band is rendered into
+//! every layer as run-length encoded rows appended to a per-layer scratch
+//! file, and its spans are dropped before the next band is traced. Once all
+//! bands are done each layer is decoded and saved exactly as the whole-plate
+//! path would save it.
+
+use crate::hollow::Hollower;
+use crate::layers::Layer;
+use crate::output::LayerWriter;
+use crate::{pixel_inside, LayerInfo, ProgressEvent, ProgressSink, Scene, SliceError, SlicerConfig};
+use rayon::prelude::*;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+const SCRATCH_DIR: &str = ".rs-licer-bands";
+
+/// Bytes per pixel assumed for the first band, before any spans are measured:
+/// one offset plus one span.
+const INITIAL_BYTES_PER_PIXEL: usize = 12;
+
+pub(crate) fn slice_tiled(
+    config: &SlicerConfig,
+    scene: &Scene,
+    layers: &[Layer],
+    output: &LayerWriter,
+    budget_mb: u32,
+    progress: &dyn ProgressSink,
+) -> Result<Vec<LayerInfo>, SliceError> {
+    let grid = &scene.grid;
+    let budget = budget_mb as usize * 1024 * 1024;
+    // Half the budget bounds span storage, the other half the full-size
+    // images held while layers are assembled. The layer checks, whose state
+    // is not bounded, are refused under a budget.
+    let span_budget = budget / 2;
+    let image_bytes = (grid.width_px as usize * grid.height_px as usize).max(1);
+    let parallel_images = (budget / 2 / image_bytes).clamp(1, rayon::current_num_threads());
+
+    // Scratch files are appended to, so never reuse ones left by a failed run.
+    let scratch = Path::new(&config.output_dir).join(SCRATCH_DIR);
+    if scratch.exists() {
+        fs::remove_dir_all(&scratch).map_err(SliceError::io("Could not clear scratch directory"))?;
+    }
+    fs::create_dir_all(&scratch).map_err(SliceError::io("Could not create scratch directory"))?;
+
+    log::info!("Raytracing pixels in bands of at most {} MiB...", span_budget / (1024 * 1024));
+    progress.report(ProgressEvent::Raytracing { rows_done: 0, rows_total: grid.height_px });
+
+    // Hollowing measures distances across band edges, so each band is traced
+    // with extra rows on both sides that are not rendered.
+    let hollower = Hollower::new(config);
+    let margin = hollower.as_ref().map_or(0, |hollower| hollower.margin_rows(grid));
+
+    let mut bytes_per_row = grid.width_px as usize * INITIAL_BYTES_PER_PIXEL;
+    let mut y0 = 0;
+    while y0 < grid.height_px {
+        let band_rows = (span_budget / bytes_per_row.max(1))
+            .saturating_sub(2 * margin as usize)
+            .clamp(1, (grid.height_px - y0) as usize);
+        let band = y0..y0 + band_rows as u32;
+        let traced = band.start.saturating_sub(margin)..(band.end + margin).min(grid.height_px);
+        log::debug!("Tracing rows {}..{}", traced.start, traced.end);
+
+        let spans = scene.trace_rows(traced.clone(), &|| {})?;
+
+        layers.par_iter().try_for_each(|layer| {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(scratch_path(&scratch, layer))
+                .map_err(SliceError::io("Could not write scratch file"))?;
+            let mut writer = BufWriter::new(file);
+            let hollowed = hollower
+                .as_ref()
+                .map(|hollower| hollower.solid(grid, &spans, traced.start, layer.z));
+            let mut runs = Vec::new();
+            for y in band.start - traced.start..band.end - traced.start {
+                runs.clear();
+                let mut inside = false;
+                let mut run = 0u32;
+                for x in 0..spans.width() {
+                    let solid = match &hollowed {
+                        Some(solid) => solid[(y * spans.width() + x) as usize],
+                        None => pixel_inside(spans.pixel(x, y), layer.z),
+                    };
+                    if solid != inside {
+                        runs.push(run);
+                        inside = !inside;
+                        run = 0;
+                    }
+                    run += 1;
+                }
+                runs.push(run);
+                write_runs(&mut writer, &runs)?;
+            }
+            writer.flush().map_err(SliceError::io("Could not write scratch file"))
+        })?;
+
+        // Size later bands for the densest rows seen so far.
+        bytes_per_row = bytes_per_row.max(spans.heap_bytes() / spans.height().max(1) as usize);
+        y0 = band.end;
+        progress.report(ProgressEvent::Raytracing { rows_done: y0, rows_total: grid.height_px });
+    }
+
+    log::info!("Generating slices...");
+    let total = layers.len() as u32;
+    progress.report(ProgressEvent::Rendering { layer: 0, total });
+    let completed_layers = AtomicU32::new(0);
+    let mut written = Vec::with_capacity(layers.len());
+
+    for chunk in layers.chunks(parallel_images) {
+        let infos: Vec<LayerInfo> = chunk.par_iter().map(|layer| {
+            let path = scratch_path(&scratch, layer);
+            let file = File::open(&path).map_err(SliceError::io("Could not read scratch file"))?;
+            let mut reader = BufReader::new(file);
+            let mut img = image::GrayImage::new(grid.width_px, grid.height_px);
+            let mut runs = Vec::new();
+
+            for y in 0..grid.height_px {
+                read_runs(&mut reader, &mut runs)?;
+                let mut x = 0;
+                for (i, run) in runs.iter().enumerate() {
+                    // Runs alternate empty and solid, starting with empty.
+                    let value = if i % 2 == 1 { 255 } else { 0 };
+                    for _ in 0..*run {
+                        img.put_pixel(x, grid.height_px - 1 - y, image::Luma([value]));
+                        x += 1;
+                    }
+                }
+            }
+
+            let info = output.write(layer, img)?;
+            let _ = fs::remove_file(&path);
+
+            let completed = completed_layers.fetch_add(1, Ordering::Relaxed) + 1;
+            if completed.is_multiple_of(5) || completed == total {
+                progress.report(ProgressEvent::Rendering { layer: completed, total });
+            }
+            Ok(info)
+        }).collect::<Result<_, SliceError>>()?;
+        written.extend(infos);
+    }
+
+    fs::remove_dir_all(&scratch).map_err(SliceError::io("Could not remove scratch directory"))?;
+    Ok(written)
+}
+
+fn scratch_path(scratch: &Path, layer: &Layer) -> PathBuf {
+    scratch.join(format!("{}.rle", layer.index))
+}
+
+/// Writes one row as a run count followed by the run lengths.
+fn write_runs(writer: &mut impl Write, runs: &[u32]) -> Result<(), SliceError> {
+    let write = |writer: &mut dyn Write, value: u32| writer.write_all(&value.to_le_bytes());
+    write(writer, runs.len() as u32).map_err(SliceError::io("Could not write scratch file"))?;
+    for run in runs {
+        write(writer, *run).map_err(SliceError::io("Could not write scratch file"))?;
+    }
+    Ok(())
+}
+
+fn read_runs(reader: &mut impl Read, runs: &mut Vec<u32>) -> Result<(), SliceError> {
+    let mut read = || {
+        let mut buf = [0u8; 4];
+        reader.read_exact(&mut buf).map(|_| u32::from_le_bytes(buf))
+    };
+    let count = read().map_err(SliceError::io("Could not read scratch file"))?;
+    runs.clear();
+    for _ in 0..count {
+        runs.push(read().map_err(SliceError::io("Could not read scratch file"))?);
+    }
+    Ok(())
+}
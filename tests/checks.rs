@@ -0,0 +1,76 @@
+//! The cavity and island checks run over the layers as written.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, DrainHole, IslandCheck, SliceReport, SlicerConfig};
+
+/// Slices a 12 x 12 x 8 mm box around a sealed spherical void of radius
+/// 3 mm, with the given drain holes.
+fn box_with_void(drain_holes: Vec<DrainHole>) -> SliceReport {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [12.0, 12.0, 8.0])
+        .with(Mesh::sphere([6.0, 6.0, 4.0], 3.0, 24).inverted())
+        .write(dir.path(), "box.stl");
+    slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        cavity_check: true,
+        drain_holes,
+        ..config(&input, &dir.path().join("out"))
+    })
+    .unwrap()
+}
+
+#[test]
+fn reports_an_enclosed_cavity() {
+    let report = box_with_void(Vec::new());
+    assert_eq!(report.cavities.len(), 1, "{:?}", report.cavities);
+    let cavity = report.cavities[0];
+    assert!(!cavity.on_plate);
+    assert!(cavity.z_min_mm >= 1.0 && cavity.z_max_mm <= 7.0, "{}", cavity);
+    let sphere_mm3 = 4.0 / 3.0 * std::f32::consts::PI * 27.0;
+    assert!((cavity.volume_mm3 / sphere_mm3 - 1.0).abs() < 0.1, "{}", cavity);
+    assert!((cavity.position_mm[0] - 6.0).abs() < 0.5 && (cavity.position_mm[1] - 6.0).abs() < 0.5, "{}", cavity);
+}
+
+#[test]
+fn ignores_a_vented_cavity() {
+    // Drilled down from above the box into the top of the void.
+    let vent = DrainHole {
+        position_mm: [6.0, 6.0, 9.0],
+        direction: [0.0, 0.0, -1.0],
+        diameter_um: 2000.0,
+        depth_um: 3000.0,
+    };
+    let report = box_with_void(vec![vent]);
+    assert!(report.cavities.is_empty(), "{:?}", report.cavities);
+}
+
+#[test]
+fn reports_islands_but_not_connected_overhangs() {
+    // On a 10 x 10 x 1 mm base: a 2 x 2 mm block floating from Z 2 mm, and a
+    // 4 x 4 mm slab held up at Z 3 mm by a 1 x 1 mm post.
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [10.0, 10.0, 1.0])
+        .with(Mesh::cuboid([2.0, 2.0, 2.0], [4.0, 4.0, 3.0]))
+        .with(Mesh::cuboid([6.0, 6.0, 1.0], [7.0, 7.0, 3.0]))
+        .with(Mesh::cuboid([5.0, 5.0, 3.0], [9.0, 9.0, 3.5]))
+        .write(dir.path(), "parts.stl");
+    let output = dir.path().join("out");
+    let report = slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        islands: Some(IslandCheck { overlays: true, strict: false }),
+        ..config(&input, &output)
+    })
+    .unwrap();
+
+    assert_eq!(report.islands.len(), 1, "{:?}", report.islands);
+    let island = report.islands[0];
+    assert_eq!((island.layer, island.z_mm), (8, 2.0));
+    assert!((island.area_mm2 - 4.0).abs() < 1e-3, "{}", island);
+    assert!((island.centroid_mm[0] - 3.0).abs() < 1e-3 && (island.centroid_mm[1] - 3.0).abs() < 1e-3, "{}", island);
+    let overlays: Vec<_> = std::fs::read_dir(output.join("islands")).unwrap().map(|e| e.unwrap().file_name()).collect();
+    assert_eq!(overlays, [report.layers[8].file.as_str()]);
+}
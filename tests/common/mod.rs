@@ -0,0 +1,145 @@
+//! Meshes and helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+use rs_licer::SlicerConfig;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+type Point = [f32; 3];
+
+/// A closed mesh as triangles, each wound so its normal points out of the
+/// solid.
+#[derive(Debug, Clone, Default)]
+pub struct Mesh(pub Vec<[Point; 3]>);
+
+impl Mesh {
+    /// An axis-aligned box.
+    pub fn cuboid(min: Point, max: Point) -> Self {
+        let corner = |i: usize| {
+            [
+                if i & 1 == 0 { min[0] } else { max[0] },
+                if i & 2 == 0 { min[1] } else { max[1] },
+                if i & 4 == 0 { min[2] } else { max[2] },
+            ]
+        };
+        let quads = [[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+        let triangles = quads
+            .iter()
+            .flat_map(|q| [[q[0], q[1], q[2]], [q[0], q[2], q[3]]])
+            .map(|t| t.map(corner))
+            .collect();
+        Self(triangles).facing_out_from(centre(min, max))
+    }
+
+    /// A sphere of `stacks` bands of `stacks * 2` quads each.
+    pub fn sphere(center: Point, radius: f32, stacks: usize) -> Self {
+        let slices = stacks * 2;
+        let point = |i: usize, j: usize| {
+            let theta = std::f32::consts::PI * i as f32 / stacks as f32;
+            let phi = std::f32::consts::TAU * (j % slices) as f32 / slices as f32;
+            [
+                center[0] + radius * theta.sin() * phi.cos(),
+                center[1] + radius * theta.sin() * phi.sin(),
+                center[2] + radius * theta.cos(),
+            ]
+        };
+        let mut triangles = Vec::new();
+        for i in 0..stacks {
+            for j in 0..slices {
+                let (a, b, c, d) = (point(i, j), point(i + 1, j), point(i + 1, j + 1), point(i, j + 1));
+                // The quads at the poles have one corner twice.
+                if i != 0 {
+                    triangles.push([a, b, d]);
+                }
+                if i != stacks - 1 {
+                    triangles.push([b, c, d]);
+                }
+            }
+        }
+        Self(triangles).facing_out_from(center)
+    }
+
+    /// Turns the mesh inside out, making it the wall of a cavity when
+    /// placed inside another.
+    pub fn inverted(self) -> Self {
+        Self(self.0.into_iter().map(|[a, b, c]| [a, c, b]).collect())
+    }
+
+    pub fn with(mut self, other: Mesh) -> Self {
+        self.0.extend(other.0);
+        self
+    }
+
+    /// Writes the mesh as a binary STL named `name` in `dir`.
+    pub fn write(&self, dir: &Path, name: &str) -> String {
+        let faces: Vec<stl_io::Triangle> = self
+            .0
+            .iter()
+            .map(|&[a, b, c]| stl_io::Triangle {
+                normal: stl_io::Normal::new(normal(a, b, c)),
+                vertices: [stl_io::Vertex::new(a), stl_io::Vertex::new(b), stl_io::Vertex::new(c)],
+            })
+            .collect();
+        let path = dir.join(name);
+        let mut file = std::fs::File::create(&path).expect("fixture is writable");
+        stl_io::write_stl(&mut file, faces.iter()).expect("fixture is writable");
+        path.to_str().expect("temporary path is UTF-8").to_string()
+    }
+
+    /// Winds every triangle of a convex mesh away from `inside`.
+    fn facing_out_from(self, inside: Point) -> Self {
+        Self(
+            self.0
+                .into_iter()
+                .map(|[a, b, c]| {
+                    let n = normal(a, b, c);
+                    let mid = centre(a, centre(b, c));
+                    let out: f32 = (0..3).map(|k| n[k] * (mid[k] - inside[k])).sum();
+                    if out < 0.0 { [a, c, b] } else { [a, b, c] }
+                })
+                .collect(),
+        )
+    }
+}
+
+fn centre(a: Point, b: Point) -> Point {
+    [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0, (a[2] + b[2]) / 2.0]
+}
+
+fn normal(a: Point, b: Point, c: Point) -> Point {
+    let (u, v) = ([b[0] - a[0], b[1] - a[1], b[2] - a[2]], [c[0] - a[0], c[1] - a[1], c[2] - a[2]]);
+    [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]]
+}
+
+/// A config slicing `input` into `output` with the defaults, quietly: the
+/// checks off and nothing opened.
+pub fn config(input: &str, output: &Path) -> SlicerConfig {
+    SlicerConfig {
+        input_path: input.to_string(),
+        output_dir: output.to_str().expect("temporary path is UTF-8").to_string(),
+        cavity_check: false,
+        islands: None,
+        ..Default::default()
+    }
+}
+
+/// Contents of every layer image in `dir`, by file name.
+pub fn layer_files(dir: &Path) -> BTreeMap<String, Vec<u8>> {
+    std::fs::read_dir(dir)
+        .expect("output directory exists")
+        .map(|entry| entry.expect("output directory is readable").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, std::fs::read(&path).expect("layer is readable"))
+        })
+        .collect()
+}
+
+/// A layer image as lit flags, row by row from the top of the image.
+pub fn lit_pixels(path: &Path) -> (u32, u32, Vec<bool>) {
+    let img = image::open(path).expect("layer is a readable image").to_luma8();
+    let (width, height) = img.dimensions();
+    (width, height, img.pixels().map(|pixel| pixel.0[0] != 0).collect())
+}
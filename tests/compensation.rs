@@ -0,0 +1,91 @@
+//! XY offsets must be able to grow a part past the edges of its model
+//! without moving the model bounds in the manifest, and no correction may
+//! change the raft drawn under it.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, ElephantFoot, LayerInfo, Raft, SlicerConfig, XyOffset};
+
+/// Slices a 4 mm cube at 100 µm pixels and returns its middle layer.
+fn middle_layer(xy_offset: XyOffset) -> LayerInfo {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    let output = dir.path().join("out");
+    let report = slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        xy_offset,
+        ..config(&input, &output)
+    })
+    .unwrap();
+    report.layers[report.layers.len() / 2].clone()
+}
+
+fn bbox_size(layer: &LayerInfo) -> (u32, u32) {
+    let [x0, y0, x1, y1] = layer.bbox_px.expect("layer is lit");
+    (x1 - x0 + 1, y1 - y0 + 1)
+}
+
+#[test]
+fn positive_offset_grows_past_the_model_bounds() {
+    let plain = middle_layer(XyOffset::default());
+    assert_eq!(bbox_size(&plain), (40, 40));
+
+    // 300 µm is three pixels on every side, with the corners rounded.
+    for offset in [XyOffset::uniform(300.0), XyOffset { outer_um: 300.0, hole_um: -100.0 }] {
+        let grown = middle_layer(offset);
+        assert_eq!(bbox_size(&grown), (46, 46), "{:?}", offset);
+        let rounded_mm2 = 16.0 + 4.0 * 4.0 * 0.3 + std::f32::consts::PI * 0.3 * 0.3;
+        assert!((grown.area_mm2 - rounded_mm2).abs() < 0.2, "{:?} grows to {} mm²", offset, grown.area_mm2);
+    }
+}
+
+#[test]
+fn corrections_leave_the_raft_alone() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    let bottom_layer = |name: &str, xy_offset, elephant_foot| {
+        let output = dir.path().join(name);
+        let report = slice(SlicerConfig {
+            pixel_size_um: 100.0,
+            layer_height_um: 250.0,
+            raft: Some(Raft::default()),
+            xy_offset,
+            elephant_foot,
+            ..config(&input, &output)
+        })
+        .unwrap();
+        report.layers[0].clone()
+    };
+    let plain = bottom_layer("plain", XyOffset::default(), None);
+    let corrected = bottom_layer(
+        "corrected",
+        XyOffset::uniform(-200.0),
+        Some(ElephantFoot { erosion_um: 300.0, ..ElephantFoot::default() }),
+    );
+    assert_eq!(corrected.area_mm2, plain.area_mm2);
+    assert_eq!(bbox_size(&corrected), bbox_size(&plain));
+}
+
+#[test]
+fn manifest_bounds_leave_out_the_padding() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    let output = dir.path().join("out");
+    slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        raft: Some(Raft::default()),
+        xy_offset: XyOffset::uniform(300.0),
+        ..config(&input, &output)
+    })
+    .unwrap();
+    let manifest: serde_json::Value =
+        serde_json::from_reader(std::fs::File::open(output.join("manifest.json")).unwrap()).unwrap();
+    // The model is lifted onto the 1 mm raft.
+    assert_eq!(manifest["bounds"]["min_mm"], serde_json::json!([0.0, 0.0, 1.0]));
+    assert_eq!(manifest["bounds"]["max_mm"], serde_json::json!([4.0, 4.0, 2.0]));
+    let origin = manifest["transform"]["origin_mm"][0].as_f64().unwrap();
+    assert!(origin <= -2.3, "plate starts at {}", origin);
+}
@@ -0,0 +1,59 @@
+//! Automatic drain holes must find cavities however they are closed off.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, AutoDrainHoles, Raft, SliceReport, SlicerConfig};
+use serde_json::Value;
+
+/// A 10 x 10 x 6 mm box open underneath, with 1 mm walls and a 2 mm roof.
+fn open_box() -> Mesh {
+    Mesh::cuboid([0.0, 0.0, 4.0], [10.0, 10.0, 6.0])
+        .with(Mesh::cuboid([0.0, 0.0, 0.0], [1.0, 10.0, 4.0]))
+        .with(Mesh::cuboid([9.0, 0.0, 0.0], [10.0, 10.0, 4.0]))
+        .with(Mesh::cuboid([1.0, 0.0, 0.0], [9.0, 1.0, 4.0]))
+        .with(Mesh::cuboid([1.0, 9.0, 0.0], [9.0, 10.0, 4.0]))
+}
+
+/// Slices the open box and returns the report and the drain holes listed in
+/// the manifest.
+fn slice_open_box(raft: Option<Raft>, auto_drain_holes: Option<AutoDrainHoles>) -> (SliceReport, Vec<Value>) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = open_box().write(dir.path(), "box.stl");
+    let output = dir.path().join("out");
+    let report = slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        raft,
+        auto_drain_holes,
+        ..config(&input, &output)
+    })
+    .unwrap();
+    let manifest = std::fs::read_to_string(output.join("manifest.json")).unwrap();
+    let manifest: Value = serde_json::from_str(&manifest).unwrap();
+    (report, manifest["drain_holes"].as_array().cloned().unwrap_or_default())
+}
+
+#[test]
+fn drains_a_cavity_sealed_by_the_raft() {
+    let auto = Some(AutoDrainHoles { diameter_um: 2000.0 });
+    let (drained, holes) = slice_open_box(Some(Raft::default()), auto);
+    let (sealed, _) = slice_open_box(Some(Raft::default()), None);
+
+    // The raft lifts the box 1 mm, so the cavity starts at Z 1 mm.
+    assert_eq!(holes.len(), 1, "{:?}", holes);
+    let coordinate = |key: &str, i: usize| holes[0][key][i].as_f64().unwrap();
+    let (x, y, z) = (coordinate("position_mm", 0), coordinate("position_mm", 1), coordinate("position_mm", 2));
+    assert!((1.0..9.0).contains(&x) && (1.0..9.0).contains(&y), "hole at ({}, {})", x, y);
+    assert!(z < 0.0 && z + holes[0]["depth_um"].as_f64().unwrap() / 1000.0 > 1.0, "hole from Z {}", z);
+    // The hole is punched through the raft's bottom layer.
+    assert!(drained.layers[0].lit_pixels < sealed.layers[0].lit_pixels);
+}
+
+#[test]
+fn leaves_a_cavity_open_to_the_plate() {
+    // Without a raft the box only seals against the plate, where no hole
+    // could drain it.
+    let (_, holes) = slice_open_box(None, Some(AutoDrainHoles::default()));
+    assert!(holes.is_empty(), "{:?}", holes);
+}
@@ -0,0 +1,61 @@
+//! The raycast and scanline engines must render the same layers.
+//!
+//! Both engines sample the same pixel centers, so a pixel can only differ
+//! where its center lies within rounding of the mesh: where the surface
+//! crosses the layer plane within the 0.1 µm each engine allows, which the
+//! raycast engine counts as solid and the scanline engine, cutting just
+//! above the plane, may not, or where a ray grazes an edge whose triangles
+//! face opposite ways.
+
+mod common;
+
+use common::{config, lit_pixels, Mesh};
+use rs_licer::{slice, SliceEngine, SlicerConfig};
+use std::path::Path;
+
+/// Largest share of a layer's pixels allowed to differ between engines.
+const TOLERANCE: f64 = 0.001;
+
+fn assert_engines_agree(mesh: Mesh) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = mesh.write(dir.path(), "model.stl");
+    let settings = |output: &Path, engine| SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 100.0,
+        engine,
+        ..config(&input, output)
+    };
+    let (raycast_dir, scanline_dir) = (dir.path().join("raycast"), dir.path().join("scanline"));
+    let raycast = slice(settings(&raycast_dir, SliceEngine::Raycast)).unwrap();
+    slice(settings(&scanline_dir, SliceEngine::Scanline)).unwrap();
+
+    for layer in &raycast.layers {
+        let a = lit_pixels(&raycast_dir.join(&layer.file));
+        let b = lit_pixels(&scanline_dir.join(&layer.file));
+        assert_eq!((a.0, a.1), (b.0, b.1), "layer {} has different dimensions", layer.file);
+        let differing = a.2.iter().zip(&b.2).filter(|(p, q)| p != q).count();
+        assert!(
+            differing as f64 <= TOLERANCE * a.2.len() as f64,
+            "{} of {} pixels differ in layer {}",
+            differing,
+            a.2.len(),
+            layer.file
+        );
+    }
+}
+
+#[test]
+fn engines_agree_on_box() {
+    assert_engines_agree(Mesh::cuboid([0.0, 0.0, 0.0], [10.0, 8.0, 5.0]));
+}
+
+#[test]
+fn engines_agree_on_sphere() {
+    assert_engines_agree(Mesh::sphere([6.0, 6.0, 5.0], 5.0, 32));
+}
+
+#[test]
+fn engines_agree_on_sealed_cavity() {
+    let outer = Mesh::cuboid([0.0, 0.0, 0.0], [12.0, 12.0, 8.0]);
+    assert_engines_agree(outer.with(Mesh::sphere([6.0, 6.0, 4.0], 3.0, 24).inverted()));
+}
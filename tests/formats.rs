@@ -0,0 +1,76 @@
+//! Layers are 8-bit PNGs unless asked otherwise, and a TIFF stack must hold
+//! every layer as a page, in layer order.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, LayerFormat, SlicerConfig};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use tiff::decoder::Decoder;
+use tiff::tags::Tag;
+
+const PAGE_NUMBER: u16 = 297;
+
+#[test]
+fn layers_are_gray_pngs_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    for (format, bit_depth) in [(LayerFormat::default(), 8), (LayerFormat::BilevelPng, 1)] {
+        let output = dir.path().join(format!("{:?}", format));
+        let report = slice(SlicerConfig {
+            pixel_size_um: 100.0,
+            layer_height_um: 250.0,
+            layer_format: format,
+            ..config(&input, &output)
+        })
+        .unwrap();
+        // The bit depth follows the signature, the IHDR chunk's header and
+        // the width and height.
+        let png = std::fs::read(output.join(&report.layers[0].file)).unwrap();
+        assert_eq!(png[24], bit_depth, "{}", format);
+    }
+}
+
+#[test]
+fn stack_pages_follow_the_layers() {
+    // A sphere, so every layer lights a different number of pixels.
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::sphere([0.0, 0.0, 2.0], 2.0, 24).write(dir.path(), "sphere.stl");
+    let output = dir.path().join("out");
+    let report = slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 100.0,
+        layer_format: LayerFormat::TiffStack,
+        ..config(&input, &output)
+    })
+    .unwrap();
+    let total = report.layers.len();
+    assert!(total > 30);
+
+    let path = output.join("layers.tif");
+    let mut decoder = Decoder::new(File::open(&path).unwrap()).unwrap();
+    let mut file = File::open(&path).unwrap();
+    for (index, layer) in report.layers.iter().enumerate() {
+        if index > 0 {
+            assert!(decoder.more_images(), "stack ends after {} pages", index);
+            decoder.next_image().unwrap();
+        }
+        let number = decoder.get_tag_u32_vec(Tag::Unknown(PAGE_NUMBER)).unwrap();
+        assert_eq!(number, [index as u32, total as u32]);
+
+        let (width, _) = decoder.dimensions().unwrap();
+        let offset = decoder.get_tag_u32(Tag::StripOffsets).unwrap();
+        let length = decoder.get_tag_u32(Tag::StripByteCounts).unwrap();
+        let mut page = vec![0; length as usize];
+        file.seek(SeekFrom::Start(offset as u64)).unwrap();
+        file.read_exact(&mut page).unwrap();
+        let mut lit = 0;
+        fax::decoder::decode_g4(page.into_iter(), width as u16, None, |changes| {
+            lit += fax::decoder::pels(changes, width as u16).filter(|&c| c == fax::Color::Black).count();
+        })
+        .unwrap();
+        assert_eq!(lit as u64, layer.lit_pixels, "page {}", index);
+    }
+    assert!(!decoder.more_images());
+}
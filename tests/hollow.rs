@@ -0,0 +1,76 @@
+//! Hollowing must leave walls of the requested thickness around a sealed
+//! cavity, filled to the requested infill density.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, Hollowing, Infill, InfillPattern, SliceReport, SlicerConfig};
+
+/// Hollows a 10 x 10 x 6 mm box to 1 mm walls at 100 µm pixels.
+fn hollow_box(cavity_check: bool, infill: Option<Infill>) -> SliceReport {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [10.0, 10.0, 6.0]).write(dir.path(), "box.stl");
+    slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        hollowing: Some(Hollowing { wall_thickness_um: 1000.0, infill }),
+        cavity_check,
+        ..config(&input, &dir.path().join("out"))
+    })
+    .unwrap()
+}
+
+/// Pixels in a layer of the box.
+const FULL: u64 = 100 * 100;
+
+#[test]
+fn leaves_walls_of_the_wall_thickness() {
+    let report = hollow_box(false, None);
+    let layers = &report.layers;
+    assert_eq!(layers.len(), 24);
+
+    // The floor and roof are whole layers, the sides whole pixels, so each
+    // is within a layer or a pixel of 1 mm.
+    let floor = layers.iter().take_while(|l| l.lit_pixels == FULL).count();
+    let roof = layers.iter().rev().take_while(|l| l.lit_pixels == FULL).count();
+    for (wall, count) in [("floor", floor), ("roof", roof)] {
+        assert!((count as f32 * 0.25 - 1.0).abs() <= 0.25, "{} is {} layers thick", wall, count);
+    }
+
+    let rims = &layers[floor..layers.len() - roof];
+    let side = (0..50u64).find(|side| FULL - (100 - 2 * side).pow(2) == rims[0].lit_pixels);
+    assert!(side.is_some_and(|side| (side as f32 * 0.1 - 1.0).abs() <= 0.1), "sides are {:?} pixels thick", side);
+    assert!(rims.iter().all(|l| l.lit_pixels == rims[0].lit_pixels));
+}
+
+#[test]
+fn reports_the_hollow_as_an_enclosed_cavity() {
+    let report = hollow_box(true, None);
+    let hollow: Vec<_> = report.layers.iter().filter(|l| l.lit_pixels < FULL).collect();
+    let volume_mm3: f32 = hollow.iter().map(|l| (FULL - l.lit_pixels) as f32 * 0.01 * l.thickness_mm).sum();
+
+    assert_eq!(report.cavities.len(), 1, "{:?}", report.cavities);
+    let cavity = report.cavities[0];
+    assert!(!cavity.on_plate);
+    assert_eq!((cavity.first_layer, cavity.last_layer), (hollow[0].index, hollow[hollow.len() - 1].index));
+    assert!((cavity.volume_mm3 - volume_mm3).abs() < 0.01, "{} mm³ against {} mm³", cavity.volume_mm3, volume_mm3);
+    assert!((cavity.position_mm[0] - 5.0).abs() < 0.1 && (cavity.position_mm[1] - 5.0).abs() < 0.1);
+}
+
+#[test]
+fn infill_fills_its_density_of_the_hollow() {
+    let empty = hollow_box(false, None);
+    for pattern in InfillPattern::ALL {
+        let infill = Infill { pattern, density_pct: 20.0, cell_size_um: 2000.0 };
+        let filled = hollow_box(false, Some(infill));
+        let (mut hollow, mut infilled) = (0, 0);
+        for (a, b) in empty.layers.iter().zip(&filled.layers) {
+            hollow += FULL - a.lit_pixels;
+            infilled += b.lit_pixels - a.lit_pixels;
+        }
+        // The hollow is not a whole number of cells, so allow for the part
+        // of a cell cut off at its edges.
+        let density = infilled as f32 / hollow as f32;
+        assert!((density - 0.2).abs() < 0.05, "{:?} fills {:.1}%", pattern, density * 100.0);
+    }
+}
@@ -0,0 +1,35 @@
+//! Progress reported after the last layer must not move the bar back.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice_with_progress, ProgressEvent, SlicerConfig, SvgOutput};
+use std::sync::Mutex;
+
+#[test]
+fn files_written_after_the_layers_keep_the_bar_full() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    let events = Mutex::new(Vec::new());
+    let sink = |event: ProgressEvent| events.lock().unwrap().push(event);
+    slice_with_progress(
+        SlicerConfig {
+            pixel_size_um: 100.0,
+            layer_height_um: 250.0,
+            svg_output: SvgOutput::Combined,
+            ..config(&input, &dir.path().join("out"))
+        },
+        &sink,
+    )
+    .unwrap();
+
+    let events = events.into_inner().unwrap();
+    let rendered = events
+        .iter()
+        .position(|event| matches!(event, ProgressEvent::Rendering { layer, total } if layer == total))
+        .expect("every layer is reported");
+    assert!(events[rendered..].iter().any(|event| matches!(event, ProgressEvent::Finishing)));
+    for event in &events[rendered..] {
+        assert_eq!(event.overall_fraction(), 1.0, "{}", event);
+    }
+}
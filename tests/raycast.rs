@@ -0,0 +1,24 @@
+//! Rays through the edges and vertices of a mesh must be counted once.
+
+mod common;
+
+use common::{config, Mesh};
+use rs_licer::{slice, SlicerConfig};
+
+#[test]
+fn lights_pixels_on_shared_edges() {
+    // The top and bottom of the box are split along diagonals running
+    // through the centers of 40 pixels.
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 1.0]).write(dir.path(), "cube.stl");
+    let report = slice(SlicerConfig {
+        pixel_size_um: 100.0,
+        layer_height_um: 250.0,
+        ..config(&input, &dir.path().join("out"))
+    })
+    .unwrap();
+    assert_eq!(report.layers.len(), 4);
+    for layer in &report.layers {
+        assert_eq!(layer.lit_pixels, 40 * 40, "layer {}", layer.file);
+    }
+}
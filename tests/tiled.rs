@@ -0,0 +1,60 @@
+//! Slicing in bands under a memory budget must write the same files as
+//! slicing the whole plate at once.
+
+mod common;
+
+use common::{config, layer_files, Mesh};
+use rs_licer::{slice, Hollowing, SliceError, SlicerConfig};
+use std::path::Path;
+
+/// Slices a bar and a sphere side by side, 1660 x 120 pixels at 50 µm,
+/// with and without a 1 MiB budget and compares every layer file. The first
+/// band holds 26 rows less twice any hollowing margin, so band edges cross
+/// both parts.
+fn assert_bands_match(hollowing: Option<Hollowing>) {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [60.0, 6.0, 3.0])
+        .with(Mesh::sphere([80.0, 3.0, 3.0], 2.9, 24))
+        .write(dir.path(), "parts.stl");
+    let settings = |output: &Path, memory_budget_mb| SlicerConfig {
+        pixel_size_um: 50.0,
+        layer_height_um: 200.0,
+        memory_budget_mb,
+        hollowing,
+        ..config(&input, output)
+    };
+    let (whole_dir, banded_dir) = (dir.path().join("whole"), dir.path().join("banded"));
+    slice(settings(&whole_dir, None)).unwrap();
+    slice(settings(&banded_dir, Some(1))).unwrap();
+
+    let (whole, banded) = (layer_files(&whole_dir), layer_files(&banded_dir));
+    assert_eq!(whole.len(), 30);
+    assert_eq!(whole.keys().collect::<Vec<_>>(), banded.keys().collect::<Vec<_>>());
+    for (name, bytes) in &whole {
+        assert!(bytes == &banded[name], "layer {} differs", name);
+    }
+}
+
+#[test]
+fn banded_layers_match_whole_plate() {
+    assert_bands_match(None);
+}
+
+#[test]
+fn banded_hollow_layers_match_whole_plate() {
+    // A 250 µm wall gives a margin of 6 rows, leaving 14 rows per band.
+    assert_bands_match(Some(Hollowing { wall_thickness_um: 250.0, infill: None }));
+}
+
+#[test]
+fn checks_are_refused_under_a_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = Mesh::cuboid([0.0, 0.0, 0.0], [4.0, 4.0, 2.0]).write(dir.path(), "cube.stl");
+    let output = dir.path().join("out");
+    let result = slice(SlicerConfig {
+        memory_budget_mb: Some(1),
+        cavity_check: true,
+        ..config(&input, &output)
+    });
+    assert!(matches!(result, Err(SliceError::InvalidConfig(_))));
+}